@@ -0,0 +1,323 @@
+//! Device builder.
+
+use gl;
+use glutin;
+use glutin::GlContext as GlContextTrait;
+use luminance_windowing::{WindowDim, WindowOpt};
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_void};
+
+use framebuffer::FramebufferOpt;
+use input::InputStreams;
+use limiter::FrameLimiter;
+use monitor::{self, Monitor};
+use state::InputState;
+use stats::FrameStats;
+use text::TextInput;
+use vsync::{self, Vsync};
+use {ContextOpt, CreationError, DeviceError, GlutinDevice};
+
+/// Builder of `GlutinDevice`.
+///
+/// Gathers the window and context options and creates both of them in a single `build` call.
+/// `Device::new` is a shortcut for a builder with default options plus the dimensions, title and
+/// window options you pass to it.
+///
+/// Resizability, always-on-top and the window icon can’t be set, as glutin 0.12 doesn’t support
+/// them: windows are always resizable, never on top, and use the platform’s default icon.
+pub struct GlutinDeviceBuilder {
+  events_loop: glutin::EventsLoop,
+  title: String,
+  dim: WindowDim,
+  min_dim: Option<(u32, u32)>,
+  max_dim: Option<(u32, u32)>,
+  decorations: bool,
+  transparent: bool,
+  visible: bool,
+  win_opt: WindowOpt,
+  ctx_opt: ContextOpt,
+  fb_opt: FramebufferOpt,
+  monitor: Option<Monitor>,
+}
+
+impl GlutinDeviceBuilder {
+  /// Create a builder with default options.
+  pub fn new() -> Self {
+    GlutinDeviceBuilder {
+      events_loop: glutin::EventsLoop::new(),
+      title: "luminance".to_owned(),
+      dim: WindowDim::Windowed(800, 600),
+      min_dim: None,
+      max_dim: None,
+      decorations: true,
+      transparent: false,
+      visible: true,
+      win_opt: WindowOpt::default(),
+      ctx_opt: ContextOpt::default(),
+      fb_opt: FramebufferOpt::default(),
+      monitor: None,
+    }
+  }
+
+  /// Set the title of the window.
+  pub fn with_title<T>(self, title: T) -> Self where T: Into<String> {
+    GlutinDeviceBuilder { title: title.into(), ..self }
+  }
+
+  /// Set the dimensions of the window.
+  ///
+  /// Fullscreen is borderless: the video mode of the monitor is never changed. With
+  /// `WindowDim::FullscreenRestricted`, the window covers the monitor and the default framebuffer
+  /// gets the requested size on platforms that honor it.
+  pub fn with_dim(self, dim: WindowDim) -> Self {
+    GlutinDeviceBuilder { dim, ..self }
+  }
+
+  /// Set the minimum dimensions the window can be resized to.
+  pub fn with_min_dimensions(self, w: u32, h: u32) -> Self {
+    GlutinDeviceBuilder { min_dim: Some((w, h)), ..self }
+  }
+
+  /// Set the maximum dimensions the window can be resized to.
+  pub fn with_max_dimensions(self, w: u32, h: u32) -> Self {
+    GlutinDeviceBuilder { max_dim: Some((w, h)), ..self }
+  }
+
+  /// Enable or disable the window decorations (borders, title bar, etc.).
+  pub fn with_decorations(self, decorations: bool) -> Self {
+    GlutinDeviceBuilder { decorations, ..self }
+  }
+
+  /// Enable or disable the transparency of the window background.
+  pub fn with_transparency(self, transparent: bool) -> Self {
+    GlutinDeviceBuilder { transparent, ..self }
+  }
+
+  /// Show or hide the window.
+  ///
+  /// A hidden window still has a working context, which is handy to render offscreen on machines
+  /// that have a display server. See `GlutinHeadlessDevice` for machines that don’t.
+  pub fn with_visibility(self, visible: bool) -> Self {
+    GlutinDeviceBuilder { visible, ..self }
+  }
+
+  /// Set the window options.
+  pub fn with_window_opt(self, win_opt: WindowOpt) -> Self {
+    GlutinDeviceBuilder { win_opt, ..self }
+  }
+
+  /// Set the OpenGL context options.
+  pub fn with_context_opt(self, ctx_opt: ContextOpt) -> Self {
+    GlutinDeviceBuilder { ctx_opt, ..self }
+  }
+
+  /// Set the default framebuffer format options.
+  pub fn with_framebuffer_opt(self, fb_opt: FramebufferOpt) -> Self {
+    GlutinDeviceBuilder { fb_opt, ..self }
+  }
+
+  /// Set the monitor to go fullscreen on, if the dimensions are fullscreen.
+  ///
+  /// The primary monitor is used by default.
+  pub fn with_monitor(self, monitor: Monitor) -> Self {
+    GlutinDeviceBuilder { monitor: Some(monitor), ..self }
+  }
+
+  /// Enumerate the available monitors.
+  pub fn monitors(&self) -> Vec<Monitor> {
+    monitor::monitors(&self.events_loop)
+  }
+
+  /// Create the window, its context and the device.
+  pub fn build(self) -> Result<GlutinDevice, DeviceError> {
+    let events_loop = self.events_loop;
+
+    let fullscreen_monitor =
+      match self.monitor {
+        Some(ref monitor) => monitor::monitor_id(&events_loop, monitor).ok_or(DeviceError::NoMonitor)?,
+        None => events_loop.get_primary_monitor()
+      };
+
+    // create the OpenGL window by creating a window, a context and attaching it to the window
+    let mut window =
+      glutin::WindowBuilder::new()
+        .with_title(self.title)
+        .with_decorations(self.decorations)
+        .with_transparency(self.transparent)
+        .with_visibility(self.visible);
+
+    if let Some((w, h)) = self.min_dim {
+      window = window.with_min_dimensions(w, h);
+    }
+
+    if let Some((w, h)) = self.max_dim {
+      window = window.with_max_dimensions(w, h);
+    }
+
+    let window =
+      match self.dim {
+        WindowDim::Windowed(w, h) => window.with_dimensions(w, h),
+        WindowDim::Fullscreen => window.with_fullscreen(Some(fullscreen_monitor)),
+        WindowDim::FullscreenRestricted(w, h) => window.with_dimensions(w, h).with_fullscreen(Some(fullscreen_monitor))
+      };
+
+    let gl_window = create_gl_window(window, &self.ctx_opt, &self.fb_opt, &events_loop).map_err(DeviceError::CreationError)?;
+
+    let cursor_hidden = self.win_opt.is_cursor_hidden();
+
+    if cursor_hidden {
+      gl_window.set_cursor(glutin::MouseCursor::NoneCursor);
+    } else {
+      gl_window.set_cursor(glutin::MouseCursor::Default);
+    }
+
+    let gl_version = load_gl(&gl_window, &self.ctx_opt)?;
+    let pixel_format = gl_window.get_pixel_format();
+
+    // glutin only knows about vsync on or off and doesn’t tell whether it worked, so we set the
+    // swap interval again ourselves where we can; adaptive vsync falls back to regular vsync
+    let vsync =
+      self.ctx_opt.vsync().and_then(|vsync| {
+        match vsync::set_swap_interval(&gl_window, vsync) {
+          Some(true) => Some(vsync),
+
+          Some(false) if vsync == Vsync::Adaptive => {
+            Some(Vsync::On).filter(|&vsync| vsync::set_swap_interval(&gl_window, vsync) == Some(true))
+          }
+
+          Some(false) => None,
+
+          // we have to trust glutin, which only knows about on and off
+          None if vsync == Vsync::Adaptive => Some(Vsync::On),
+          None => Some(vsync)
+        }
+      });
+
+    let device =
+      GlutinDevice {
+        window: gl_window,
+        events_loop,
+        gl_version,
+        vsync,
+        pixel_format,
+        closed: false,
+        input_streams: InputStreams::new(),
+        input_state: InputState::new(),
+        resized: None,
+        hidpi_factor_changed: None,
+        recorder: None,
+        record_error: None,
+        frame_stats: FrameStats::new(),
+        gpu_timer: None,
+        limiter: FrameLimiter::new(),
+        frame_period: None,
+        unfocused_frame_period: None,
+        focused: true,
+        cursor_icon: glutin::MouseCursor::Default,
+        cursor_hidden,
+        cursor_grabbed: false,
+        gamepads: None,
+        text_input: TextInput::new()
+      };
+
+    Ok(device)
+  }
+}
+
+impl Default for GlutinDeviceBuilder {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+/// Create the window and its context, trying each requested version in turn.
+fn create_gl_window(
+  window: glutin::WindowBuilder,
+  ctx_opt: &ContextOpt,
+  fb_opt: &FramebufferOpt,
+  events_loop: &glutin::EventsLoop
+) -> Result<glutin::GlWindow, CreationError> {
+  let mut last_err = CreationError::OpenGlVersionNotSupported;
+
+  for gl_request in ctx_opt.gl_requests() {
+    let ctx =
+      glutin::ContextBuilder::new()
+        .with_gl(gl_request)
+        .with_gl_profile(ctx_opt.profile());
+    let ctx =
+      match ctx_opt.vsync() {
+        Some(vsync) => ctx.with_vsync(vsync != Vsync::Off),
+        None => ctx
+      };
+    let ctx = fb_opt.apply(ctx);
+
+    match glutin::GlWindow::new(window.clone(), ctx, events_loop) {
+      Ok(gl_window) => return Ok(gl_window),
+      Err(e) => last_err = e
+    }
+  }
+
+  Err(last_err)
+}
+
+/// Make a context current, load the OpenGL functions and check that the context is usable.
+///
+/// Returns the version of the context, if the driver reported it.
+pub(crate) fn load_gl<C>(ctx: &C, ctx_opt: &ContextOpt) -> Result<Option<(u8, u8)>, DeviceError> where C: GlContextTrait {
+  unsafe { ctx.make_current() }.map_err(DeviceError::ContextActivationError)?;
+  gl::load_with(|s| ctx.get_proc_address(s) as *const c_void);
+
+  let missing_functions = get_missing_gl_functions();
+
+  if !missing_functions.is_empty() {
+    return Err(DeviceError::MissingGlFunctions(missing_functions));
+  }
+
+  let gl_version = get_gl_version();
+
+  if let (Some(&required), Some(obtained)) = (ctx_opt.versions().iter().min(), gl_version) {
+    if obtained < required {
+      return Err(DeviceError::UnsupportedGlVersion { required, obtained });
+    }
+  }
+
+  Ok(gl_version)
+}
+
+/// Get the names of the OpenGL functions luminance relies on that couldn’t be loaded.
+fn get_missing_gl_functions() -> Vec<&'static str> {
+  macro_rules! check_loaded {
+    ($($f:ident),*) => {
+      vec![$((concat!("gl", stringify!($f)), gl::$f::is_loaded())),*]
+    }
+  }
+
+  let functions = check_loaded!(
+    GetString, GetIntegerv, Viewport, Clear, ClearColor, Enable, Disable, BlendFunc, DepthFunc,
+    GenBuffers, BindBuffer, BufferData, GenVertexArrays, BindVertexArray, VertexAttribPointer,
+    EnableVertexAttribArray, CreateShader, ShaderSource, CompileShader, CreateProgram,
+    AttachShader, LinkProgram, UseProgram, GetUniformLocation, GenTextures, BindTexture,
+    TexImage2D, GenFramebuffers, BindFramebuffer, FramebufferTexture2D, CheckFramebufferStatus,
+    DrawArrays, DrawElements, ReadPixels
+  );
+
+  functions.into_iter().filter(|&(_, loaded)| !loaded).map(|(name, _)| name).collect()
+}
+
+/// Get the version of the current OpenGL context by parsing `GL_VERSION`.
+///
+/// Works for both OpenGL (`"4.5.0 NVIDIA 390.25"`) and OpenGL ES (`"OpenGL ES 3.2 Mesa"`) strings.
+pub(crate) fn get_gl_version() -> Option<(u8, u8)> {
+  let version = unsafe { gl::GetString(gl::VERSION) };
+
+  if version.is_null() {
+    return None;
+  }
+
+  let version = unsafe { CStr::from_ptr(version as *const c_char) }.to_string_lossy();
+  let mut numbers = version.trim_start_matches(|c: char| !c.is_digit(10)).split(|c: char| !c.is_digit(10));
+  let major = numbers.next()?.parse().ok()?;
+  let minor = numbers.next()?.parse().ok()?;
+
+  Some((major, minor))
+}
@@ -0,0 +1,150 @@
+//! Default framebuffer readback.
+
+use gl;
+use gl::types::{GLint, GLsizei, GLuint};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::os::raw::c_void;
+use std::path::Path;
+
+/// RGBA8 image read back from the default framebuffer.
+///
+/// Rows are stored top to bottom, as in most image formats (OpenGL reads them bottom to top).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Screenshot {
+  width: u32,
+  height: u32,
+  pixels: Vec<u8>,
+}
+
+impl Screenshot {
+  pub(crate) fn new(width: u32, height: u32, pixels: Vec<u8>) -> Self {
+    Screenshot { width, height, pixels }
+  }
+
+  /// Width of the image, in pixels.
+  pub fn width(&self) -> u32 {
+    self.width
+  }
+
+  /// Height of the image, in pixels.
+  pub fn height(&self) -> u32 {
+    self.height
+  }
+
+  /// RGBA8 pixels, rows top to bottom.
+  pub fn pixels(&self) -> &[u8] {
+    &self.pixels
+  }
+
+  /// Get the RGBA8 pixels back.
+  pub fn into_pixels(self) -> Vec<u8> {
+    self.pixels
+  }
+
+  /// Write the image as a binary PPM (the alpha channel is dropped).
+  pub fn write_ppm<W>(&self, mut w: W) -> io::Result<()> where W: Write {
+    write!(w, "P6\n{} {}\n255\n", self.width, self.height)?;
+    write_rgb(&mut w, &self.pixels)
+  }
+
+  /// Save the image as a binary PPM file (the alpha channel is dropped).
+  pub fn save_ppm<P>(&self, path: P) -> io::Result<()> where P: AsRef<Path> {
+    let file = File::create(path)?;
+    self.write_ppm(BufWriter::new(file))
+  }
+}
+
+/// Write RGBA8 pixels as RGB8.
+pub(crate) fn write_rgb<W>(w: &mut W, rgba: &[u8]) -> io::Result<()> where W: Write {
+  let rgb: Vec<u8> = rgba.chunks(4).flat_map(|pixel| pixel[..3].iter().cloned()).collect();
+  w.write_all(&rgb)
+}
+
+/// Flip RGBA8 rows in place, turning OpenGL’s bottom-to-top order into top-to-bottom.
+pub(crate) fn flip_rows(pixels: &mut [u8], width: u32, height: u32) {
+  let stride = width as usize * 4;
+  let height = height as usize;
+
+  for y in 0 .. height / 2 {
+    let (top, bottom) = pixels.split_at_mut((height - 1 - y) * stride);
+    top[y * stride .. (y + 1) * stride].swap_with_slice(&mut bottom[.. stride]);
+  }
+}
+
+/// Read the current read buffer of the default framebuffer.
+///
+/// This must be done before swapping buffers, as the back buffer is undefined afterwards.
+pub(crate) fn read_default_framebuffer(width: u32, height: u32) -> Screenshot {
+  let mut pixels = vec![0; width as usize * height as usize * 4];
+
+  unsafe { read_pixels(0, width, height, pixels.as_mut_ptr() as *mut c_void) };
+  flip_rows(&mut pixels, width, height);
+
+  Screenshot::new(width, height, pixels)
+}
+
+/// Read the current read buffer of the default framebuffer as RGBA8, rows bottom to top.
+///
+/// If `pack_buffer` is not `0`, pixels are written asynchronously to that buffer and `dst` is an
+/// offset in it. The framebuffer and buffer bindings and the pack alignment are restored
+/// afterwards, so that luminance’s state tracking isn’t fooled.
+pub(crate) unsafe fn read_pixels(pack_buffer: GLuint, width: u32, height: u32, dst: *mut c_void) {
+  let mut read_fb: GLint = 0;
+  let mut previous_pack_buffer: GLint = 0;
+  let mut pack_alignment: GLint = 0;
+
+  gl::GetIntegerv(gl::READ_FRAMEBUFFER_BINDING, &mut read_fb);
+  gl::GetIntegerv(gl::PIXEL_PACK_BUFFER_BINDING, &mut previous_pack_buffer);
+  gl::GetIntegerv(gl::PACK_ALIGNMENT, &mut pack_alignment);
+
+  gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+  gl::BindBuffer(gl::PIXEL_PACK_BUFFER, pack_buffer);
+  gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+
+  gl::ReadPixels(0, 0, width as GLsizei, height as GLsizei, gl::RGBA, gl::UNSIGNED_BYTE, dst);
+
+  gl::BindFramebuffer(gl::READ_FRAMEBUFFER, read_fb as GLuint);
+  gl::BindBuffer(gl::PIXEL_PACK_BUFFER, previous_pack_buffer as GLuint);
+  gl::PixelStorei(gl::PACK_ALIGNMENT, pack_alignment);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// RGBA8 pixel whose channels all derive from a single value.
+  fn pixel(v: u8) -> [u8; 4] {
+    [v, v + 1, v + 2, 255]
+  }
+
+  fn image(values: &[u8]) -> Vec<u8> {
+    values.iter().flat_map(|&v| pixel(v).to_vec()).collect()
+  }
+
+  #[test]
+  fn flip_odd_rows() {
+    // 2×3, rows bottom to top
+    let mut pixels = image(&[0, 10, 20, 30, 40, 50]);
+    flip_rows(&mut pixels, 2, 3);
+    assert_eq!(pixels, image(&[40, 50, 20, 30, 0, 10]));
+  }
+
+  #[test]
+  fn flip_even_rows() {
+    let mut pixels = image(&[0, 10, 20, 30]);
+    flip_rows(&mut pixels, 2, 2);
+    assert_eq!(pixels, image(&[20, 30, 0, 10]));
+  }
+
+  #[test]
+  fn ppm() {
+    let screenshot = Screenshot::new(2, 1, image(&[0, 10]));
+    let mut ppm = Vec::new();
+    screenshot.write_ppm(&mut ppm).unwrap();
+
+    let mut expected = b"P6\n2 1\n255\n".to_vec();
+    expected.extend_from_slice(&[0, 1, 2, 10, 11, 12]);
+    assert_eq!(ppm, expected);
+  }
+}
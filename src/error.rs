@@ -0,0 +1,53 @@
+//! Device errors.
+
+use glutin::{ContextError, CreationError};
+use std::error::Error;
+use std::fmt;
+
+/// Error that can be risen while creating or using a `Device` object.
+#[derive(Debug)]
+pub enum DeviceError {
+  /// The window or its OpenGL context couldn’t be created.
+  CreationError(CreationError),
+  /// The OpenGL context couldn’t be made current.
+  ContextActivationError(ContextError),
+  /// Some OpenGL functions couldn’t be loaded.
+  MissingGlFunctions(Vec<&'static str>),
+  /// The OpenGL context version is lower than the lowest requested one.
+  UnsupportedGlVersion {
+    /// Lowest version that was requested.
+    required: (u8, u8),
+    /// Version that was actually obtained.
+    obtained: (u8, u8),
+  },
+  /// Fullscreen was requested on a monitor that doesn’t exist (anymore), or there is no monitor at
+  /// all.
+  NoMonitor,
+  /// The cursor couldn’t be grabbed, released or moved.
+  CursorError(String),
+}
+
+impl fmt::Display for DeviceError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+    match *self {
+      // the glutin errors are available as sources, so that they’re not reported twice
+      DeviceError::CreationError(_) => f.write_str("cannot create the window or its context"),
+      DeviceError::ContextActivationError(_) => f.write_str("cannot make the context current"),
+      DeviceError::MissingGlFunctions(ref names) => write!(f, "missing OpenGL functions: {}", names.join(", ")),
+      DeviceError::UnsupportedGlVersion { required, obtained } =>
+        write!(f, "unsupported OpenGL version {}.{} (at least {}.{} required)", obtained.0, obtained.1, required.0, required.1),
+      DeviceError::NoMonitor => f.write_str("fullscreen requested on a monitor that doesn’t exist"),
+      DeviceError::CursorError(ref e) => write!(f, "cursor error: {}", e)
+    }
+  }
+}
+
+impl Error for DeviceError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match *self {
+      DeviceError::CreationError(ref e) => Some(e),
+      DeviceError::ContextActivationError(ref e) => Some(e),
+      _ => None
+    }
+  }
+}
@@ -0,0 +1,107 @@
+//! Default framebuffer format options.
+
+use glutin;
+
+/// Highest number of samples per pixel that can be requested.
+const MAX_SAMPLES: u16 = 1 << 15;
+
+/// Default framebuffer format options.
+///
+/// These are requirements: if the platform cannot provide a framebuffer that satisfies them, the
+/// device creation fails. Use `GlutinDevice::pixel_format` to know what was actually chosen, as the
+/// platform is free to give you more than asked (e.g. a stencil buffer you didn’t request).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct FramebufferOpt {
+  color_bits: u8,
+  alpha_bits: u8,
+  depth_bits: u8,
+  stencil_bits: u8,
+  multisampling: Option<u16>,
+  srgb: bool,
+}
+
+impl FramebufferOpt {
+  /// Set the number of bits of the color channels (red, green and blue together) and of the alpha
+  /// channel.
+  pub fn with_pixel_format(self, color_bits: u8, alpha_bits: u8) -> Self {
+    FramebufferOpt { color_bits, alpha_bits, ..self }
+  }
+
+  /// Set the number of bits of the depth buffer. `0` means no depth buffer.
+  pub fn with_depth_bits(self, depth_bits: u8) -> Self {
+    FramebufferOpt { depth_bits, ..self }
+  }
+
+  /// Set the number of bits of the stencil buffer. `0` means no stencil buffer.
+  pub fn with_stencil_bits(self, stencil_bits: u8) -> Self {
+    FramebufferOpt { stencil_bits, ..self }
+  }
+
+  /// Set the number of samples per pixel, or disable multisampling with `None` or `Some(0)`.
+  ///
+  /// The number of samples is rounded up to the next power of two, up to 32768.
+  pub fn with_multisampling(self, samples: Option<u16>) -> Self {
+    let multisampling =
+      samples.filter(|&samples| samples > 0)
+        .map(|samples| samples.checked_next_power_of_two().unwrap_or(MAX_SAMPLES));
+    FramebufferOpt { multisampling, ..self }
+  }
+
+  /// Require an sRGB-capable framebuffer.
+  pub fn with_srgb(self, srgb: bool) -> Self {
+    FramebufferOpt { srgb, ..self }
+  }
+
+  /// Number of bits of the color channels.
+  pub fn color_bits(&self) -> u8 {
+    self.color_bits
+  }
+
+  /// Number of bits of the alpha channel.
+  pub fn alpha_bits(&self) -> u8 {
+    self.alpha_bits
+  }
+
+  /// Number of bits of the depth buffer.
+  pub fn depth_bits(&self) -> u8 {
+    self.depth_bits
+  }
+
+  /// Number of bits of the stencil buffer.
+  pub fn stencil_bits(&self) -> u8 {
+    self.stencil_bits
+  }
+
+  /// Number of samples per pixel, if multisampling is enabled.
+  pub fn multisampling(&self) -> Option<u16> {
+    self.multisampling
+  }
+
+  /// Whether an sRGB-capable framebuffer is required.
+  pub fn is_srgb(&self) -> bool {
+    self.srgb
+  }
+
+  /// Apply the requirements to a context builder.
+  pub(crate) fn apply<'a>(&self, ctx: glutin::ContextBuilder<'a>) -> glutin::ContextBuilder<'a> {
+    ctx.with_pixel_format(self.color_bits, self.alpha_bits)
+      .with_depth_buffer(self.depth_bits)
+      .with_stencil_buffer(self.stencil_bits)
+      .with_srgb(self.srgb)
+      .with_multisampling(self.multisampling.unwrap_or(0))
+  }
+}
+
+impl Default for FramebufferOpt {
+  /// 24-bit color, 8-bit alpha, 24-bit depth, 8-bit stencil, no multisampling, no sRGB.
+  fn default() -> Self {
+    FramebufferOpt {
+      color_bits: 24,
+      alpha_bits: 8,
+      depth_bits: 24,
+      stencil_bits: 8,
+      multisampling: None,
+      srgb: false,
+    }
+  }
+}
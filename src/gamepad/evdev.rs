@@ -0,0 +1,514 @@
+//! Linux evdev gamepad backend.
+
+use libc;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read};
+use std::mem;
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::time::{Duration, Instant};
+
+use super::{Axis, Button, GamepadBackend, GamepadEvent, GamepadId};
+use Action;
+
+// event types
+const EV_KEY: u16 = 0x01;
+const EV_ABS: u16 = 0x03;
+
+// buttons
+const BTN_JOYSTICK: u16 = 0x120;
+const BTN_GAMEPAD: u16 = 0x130;
+const BTN_SOUTH: u16 = 0x130;
+const BTN_EAST: u16 = 0x131;
+const BTN_NORTH: u16 = 0x133;
+const BTN_WEST: u16 = 0x134;
+const BTN_TL: u16 = 0x136;
+const BTN_TR: u16 = 0x137;
+const BTN_TL2: u16 = 0x138;
+const BTN_TR2: u16 = 0x139;
+const BTN_SELECT: u16 = 0x13a;
+const BTN_START: u16 = 0x13b;
+const BTN_MODE: u16 = 0x13c;
+const BTN_THUMBL: u16 = 0x13d;
+const BTN_THUMBR: u16 = 0x13e;
+const BTN_DPAD_UP: u16 = 0x220;
+const BTN_DPAD_DOWN: u16 = 0x221;
+const BTN_DPAD_LEFT: u16 = 0x222;
+const BTN_DPAD_RIGHT: u16 = 0x223;
+const KEY_MAX: usize = 0x2ff;
+
+// axes
+const ABS_X: u16 = 0x00;
+const ABS_Y: u16 = 0x01;
+const ABS_Z: u16 = 0x02;
+const ABS_RX: u16 = 0x03;
+const ABS_RY: u16 = 0x04;
+const ABS_RZ: u16 = 0x05;
+const ABS_HAT0X: u16 = 0x10;
+const ABS_HAT0Y: u16 = 0x11;
+
+/// How often the device directory is scanned for new gamepads.
+const SCAN_PERIOD_MS: u64 = 1000;
+
+/// Number of events read at once.
+const READ_EVENTS: usize = 64;
+
+/// Build an `ioctl` read request number, as `_IOR` does.
+fn ior(nr: u32, size: usize) -> u32 {
+  (2 << 30) | ((size as u32) << 16) | ((b'E' as u32) << 8) | nr
+}
+
+/// Range of an absolute axis, as reported by `EVIOCGABS`.
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+struct AbsInfo {
+  value: i32,
+  minimum: i32,
+  maximum: i32,
+  fuzz: i32,
+  flat: i32,
+  resolution: i32,
+}
+
+impl AbsInfo {
+  /// Range used when the device can’t tell (e.g. fake nodes).
+  fn default_stick() -> Self {
+    AbsInfo { value: 0, minimum: -32768, maximum: 32767, fuzz: 0, flat: 0, resolution: 0 }
+  }
+
+  /// Map a stick value to `[-1; 1]`, honoring the dead zone.
+  fn normalize_stick(&self, value: i32) -> f32 {
+    let center = (self.minimum as f32 + self.maximum as f32) * 0.5;
+    let half_range = (self.maximum as f32 - self.minimum as f32) * 0.5;
+
+    if half_range <= 0. || (value as f32 - center).abs() <= self.flat as f32 {
+      return 0.;
+    }
+
+    ((value as f32 - center) / half_range).max(-1.).min(1.)
+  }
+
+  /// Map a trigger value to `[0; 1]`.
+  fn normalize_trigger(&self, value: i32) -> f32 {
+    let range = self.maximum as f32 - self.minimum as f32;
+
+    if range <= 0. {
+      return 0.;
+    }
+
+    ((value as f32 - self.minimum as f32) / range).max(0.).min(1.)
+  }
+}
+
+/// An open gamepad node.
+struct Device {
+  id: GamepadId,
+  file: File,
+  abs_info: HashMap<u16, AbsInfo>,
+  /// Last value of the hat axes, to turn them into D-pad buttons.
+  hat: [i32; 2],
+}
+
+/// Gamepad backend reading Linux evdev nodes.
+///
+/// Every second or so, the directory is scanned for new `event*` nodes that look like gamepads or
+/// joysticks; nodes that fail to be read are reported as disconnected. Reading them usually
+/// requires being in the `input` group.
+///
+/// Nodes that don’t support evdev `ioctl`s, such as FIFOs or regular files, are accepted as
+/// gamepads with a default axis range: point `with_directory` at a directory of such files to feed
+/// fake events to the backend.
+pub struct EvdevBackend {
+  dir: PathBuf,
+  devices: HashMap<PathBuf, Device>,
+  /// Nodes that are not gamepads (keyboards, mice…), with their inode number, so that they’re not
+  /// queried again on every scan; a new node at the same path has another inode.
+  rejected: HashMap<PathBuf, u64>,
+  next_id: u32,
+  last_scan: Option<Instant>,
+}
+
+impl EvdevBackend {
+  /// Backend reading `/dev/input`.
+  pub fn new() -> Self {
+    Self::with_directory("/dev/input")
+  }
+
+  /// Backend reading the `event*` nodes of a given directory.
+  pub fn with_directory<P>(dir: P) -> Self where P: Into<PathBuf> {
+    EvdevBackend {
+      dir: dir.into(),
+      devices: HashMap::new(),
+      rejected: HashMap::new(),
+      next_id: 0,
+      last_scan: None,
+    }
+  }
+
+  /// Open the new gamepad nodes.
+  fn scan(&mut self, events: &mut Vec<GamepadEvent>) {
+    let entries =
+      match fs::read_dir(&self.dir) {
+        Ok(entries) => entries,
+        Err(_) => return
+      };
+
+    let mut nodes: Vec<_> =
+      entries.filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| is_event_node(path))
+        .filter_map(|path| fs::metadata(&path).ok().map(|metadata| (path, metadata.ino())))
+        .collect();
+    nodes.sort();
+
+    // forget the rejected nodes that are gone or were replaced
+    self.rejected.retain(|path, &mut ino| nodes.iter().any(|node| node.0 == *path && node.1 == ino));
+
+    for (path, ino) in nodes {
+      if self.devices.contains_key(&path) || self.rejected.contains_key(&path) {
+        continue;
+      }
+
+      // nodes that can’t be opened (yet) are retried, as their permissions may be set afterwards
+      match open_gamepad(&path) {
+        Ok(Some((file, name, abs_info))) => {
+          let id = GamepadId(self.next_id);
+          self.next_id += 1;
+
+          self.devices.insert(path, Device { id, file, abs_info, hat: [0, 0] });
+          events.push(GamepadEvent::Connected(id, name));
+        }
+
+        Ok(None) => {
+          self.rejected.insert(path, ino);
+        }
+
+        Err(_) => ()
+      }
+    }
+  }
+}
+
+impl Default for EvdevBackend {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl GamepadBackend for EvdevBackend {
+  fn poll(&mut self, events: &mut Vec<GamepadEvent>) {
+    let now = Instant::now();
+    let scan_due = self.last_scan.map_or(true, |last_scan| now - last_scan >= Duration::from_millis(SCAN_PERIOD_MS));
+
+    if scan_due {
+      self.last_scan = Some(now);
+      self.scan(events);
+    }
+
+    let mut disconnected = Vec::new();
+
+    for (path, device) in &mut self.devices {
+      if read_device(device, events).is_err() {
+        events.push(GamepadEvent::Disconnected(device.id));
+        disconnected.push(path.clone());
+      }
+    }
+
+    for path in disconnected {
+      self.devices.remove(&path);
+    }
+  }
+}
+
+fn is_event_node(path: &Path) -> bool {
+  path.file_name().and_then(|name| name.to_str()).map_or(false, |name| name.starts_with("event"))
+}
+
+/// Open a node if it’s a gamepad, returning it along with its name and axis ranges.
+///
+/// Returns `Ok(None)` if the node is not a gamepad, and an error if it couldn’t be queried.
+fn open_gamepad(path: &Path) -> io::Result<Option<(File, String, HashMap<u16, AbsInfo>)>> {
+  let file = OpenOptions::new().read(true).custom_flags(libc::O_NONBLOCK).open(path)?;
+  let fd = file.as_raw_fd();
+
+  // supported buttons; nodes that don’t understand the request are taken as fake gamepads
+  let mut key_bits = [0u8; KEY_MAX / 8 + 1];
+  let res = unsafe { libc::ioctl(fd, ior(0x20 + EV_KEY as u32, key_bits.len()) as _, key_bits.as_mut_ptr()) };
+
+  if res < 0 {
+    let err = io::Error::last_os_error();
+
+    return if err.raw_os_error() == Some(libc::ENOTTY) {
+      let name = path.file_name().map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+      Ok(Some((file, name, HashMap::new())))
+    } else {
+      Err(err)
+    };
+  }
+
+  let has_key = |code: u16| key_bits[code as usize / 8] & (1 << (code % 8)) != 0;
+
+  if !has_key(BTN_GAMEPAD) && !has_key(BTN_JOYSTICK) {
+    return Ok(None);
+  }
+
+  let mut name = [0u8; 256];
+  let res = unsafe { libc::ioctl(fd, ior(0x06, name.len()) as _, name.as_mut_ptr()) };
+  let name =
+    if res > 0 {
+      let len = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+      String::from_utf8_lossy(&name[..len]).into_owned()
+    } else {
+      String::new()
+    };
+
+  let mut abs_info = HashMap::new();
+
+  for &code in &[ABS_X, ABS_Y, ABS_Z, ABS_RX, ABS_RY, ABS_RZ] {
+    let mut info = AbsInfo::default_stick();
+    let res = unsafe { libc::ioctl(fd, ior(0x40 + code as u32, mem::size_of::<AbsInfo>()) as _, &mut info as *mut AbsInfo) };
+
+    if res >= 0 {
+      abs_info.insert(code, info);
+    }
+  }
+
+  Ok(Some((file, name, abs_info)))
+}
+
+/// Read the pending events of a device. Fails if the device is gone.
+fn read_device(device: &mut Device, events: &mut Vec<GamepadEvent>) -> io::Result<()> {
+  let event_size = mem::size_of::<libc::input_event>();
+  let mut buf = vec![0u8; event_size * READ_EVENTS];
+
+  loop {
+    let read =
+      match device.file.read(&mut buf) {
+        Ok(read) => read,
+        Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+        Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+        Err(e) => return Err(e)
+      };
+
+    // end of a fake node
+    if read == 0 {
+      return Ok(());
+    }
+
+    for chunk in buf[..read].chunks(event_size).filter(|chunk| chunk.len() == event_size) {
+      let event: libc::input_event = unsafe { ptr::read_unaligned(chunk.as_ptr() as *const _) };
+      translate(device, event.type_, event.code, event.value, events);
+    }
+  }
+}
+
+/// Translate a raw event into gamepad events.
+fn translate(device: &mut Device, ty: u16, code: u16, value: i32, events: &mut Vec<GamepadEvent>) {
+  let id = device.id;
+
+  match ty {
+    EV_KEY => {
+      // ignore key repeats
+      let action =
+        match value {
+          0 => Action::Released,
+          1 => Action::Pressed,
+          _ => return
+        };
+
+      if let Some(button) = map_button(code) {
+        events.push(GamepadEvent::Button(id, button, action));
+      }
+    }
+
+    EV_ABS => {
+      match code {
+        ABS_HAT0X => {
+          let previous = device.hat[0];
+          device.hat[0] = value.signum();
+          push_hat(id, previous, device.hat[0], Button::DPadLeft, Button::DPadRight, events);
+        }
+
+        ABS_HAT0Y => {
+          let previous = device.hat[1];
+          device.hat[1] = value.signum();
+          push_hat(id, previous, device.hat[1], Button::DPadUp, Button::DPadDown, events);
+        }
+
+        _ => {
+          let info = device.abs_info.get(&code).cloned().unwrap_or_else(AbsInfo::default_stick);
+
+          let axis_value =
+            match code {
+              ABS_X => Some((Axis::LeftX, info.normalize_stick(value))),
+              ABS_Y => Some((Axis::LeftY, info.normalize_stick(value))),
+              ABS_RX => Some((Axis::RightX, info.normalize_stick(value))),
+              ABS_RY => Some((Axis::RightY, info.normalize_stick(value))),
+              ABS_Z => Some((Axis::LeftTrigger, info.normalize_trigger(value))),
+              ABS_RZ => Some((Axis::RightTrigger, info.normalize_trigger(value))),
+              _ => None
+            };
+
+          if let Some((axis, value)) = axis_value {
+            events.push(GamepadEvent::Axis(id, axis, value));
+          }
+        }
+      }
+    }
+
+    _ => ()
+  }
+}
+
+/// Turn a hat axis change into D-pad button events.
+fn push_hat(
+  id: GamepadId,
+  previous: i32,
+  current: i32,
+  negative: Button,
+  positive: Button,
+  events: &mut Vec<GamepadEvent>
+) {
+  if previous == current {
+    return;
+  }
+
+  match previous {
+    -1 => events.push(GamepadEvent::Button(id, negative, Action::Released)),
+    1 => events.push(GamepadEvent::Button(id, positive, Action::Released)),
+    _ => ()
+  }
+
+  match current {
+    -1 => events.push(GamepadEvent::Button(id, negative, Action::Pressed)),
+    1 => events.push(GamepadEvent::Button(id, positive, Action::Pressed)),
+    _ => ()
+  }
+}
+
+fn map_button(code: u16) -> Option<Button> {
+  let button =
+    match code {
+      BTN_SOUTH => Button::South,
+      BTN_EAST => Button::East,
+      BTN_NORTH => Button::North,
+      BTN_WEST => Button::West,
+      BTN_TL => Button::LeftBumper,
+      BTN_TR => Button::RightBumper,
+      BTN_TL2 => Button::LeftTrigger,
+      BTN_TR2 => Button::RightTrigger,
+      BTN_SELECT => Button::Select,
+      BTN_START => Button::Start,
+      BTN_MODE => Button::Mode,
+      BTN_THUMBL => Button::LeftThumb,
+      BTN_THUMBR => Button::RightThumb,
+      BTN_DPAD_UP => Button::DPadUp,
+      BTN_DPAD_DOWN => Button::DPadDown,
+      BTN_DPAD_LEFT => Button::DPadLeft,
+      BTN_DPAD_RIGHT => Button::DPadRight,
+      _ => return None
+    };
+
+  Some(button)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::env;
+  use std::io::Write;
+  use std::process;
+  use std::slice;
+
+  fn input_event(ty: u16, code: u16, value: i32) -> libc::input_event {
+    let mut event: libc::input_event = unsafe { mem::zeroed() };
+    event.type_ = ty;
+    event.code = code;
+    event.value = value;
+    event
+  }
+
+  fn write_events(path: &Path, events: &[libc::input_event]) {
+    let bytes = unsafe { slice::from_raw_parts(events.as_ptr() as *const u8, events.len() * mem::size_of::<libc::input_event>()) };
+    File::create(path).unwrap().write_all(bytes).unwrap();
+  }
+
+  #[test]
+  fn fake_node() {
+    let dir = env::temp_dir().join(format!("luminance-glutin-evdev-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    write_events(&dir.join("event0"), &[
+      input_event(EV_KEY, BTN_SOUTH, 1),
+      input_event(EV_KEY, BTN_SOUTH, 2),
+      input_event(EV_ABS, ABS_HAT0X, -1),
+      input_event(EV_ABS, ABS_HAT0X, 1),
+      input_event(EV_ABS, ABS_HAT0Y, 0),
+      input_event(EV_ABS, ABS_HAT0X, 0),
+      input_event(EV_ABS, ABS_X, 32767),
+      input_event(EV_ABS, ABS_Z, -32768),
+      input_event(EV_KEY, BTN_SOUTH, 0),
+    ]);
+    File::create(dir.join("js0")).unwrap();
+
+    let mut backend = EvdevBackend::with_directory(&dir);
+    let mut events = Vec::new();
+    backend.poll(&mut events);
+
+    let id = GamepadId(0);
+    assert_eq!(events, vec![
+      GamepadEvent::Connected(id, "event0".to_owned()),
+      GamepadEvent::Button(id, Button::South, Action::Pressed),
+      GamepadEvent::Button(id, Button::DPadLeft, Action::Pressed),
+      GamepadEvent::Button(id, Button::DPadLeft, Action::Released),
+      GamepadEvent::Button(id, Button::DPadRight, Action::Pressed),
+      GamepadEvent::Button(id, Button::DPadRight, Action::Released),
+      GamepadEvent::Axis(id, Axis::LeftX, 1.),
+      GamepadEvent::Axis(id, Axis::LeftTrigger, 0.),
+      GamepadEvent::Button(id, Button::South, Action::Released),
+    ]);
+
+    // the node was read to the end and is not reported again
+    events.clear();
+    backend.poll(&mut events);
+    assert!(events.is_empty());
+
+    fs::remove_dir_all(&dir).unwrap();
+  }
+
+  #[test]
+  fn normalize_stick() {
+    let info = AbsInfo { value: 0, minimum: 0, maximum: 255, fuzz: 0, flat: 15, resolution: 0 };
+
+    assert_eq!(info.normalize_stick(0), -1.);
+    assert_eq!(info.normalize_stick(255), 1.);
+    assert_eq!(info.normalize_stick(-10), -1.);
+    assert_eq!(info.normalize_stick(300), 1.);
+
+    // dead zone around the center (127.5)
+    assert_eq!(info.normalize_stick(127), 0.);
+    assert_eq!(info.normalize_stick(142), 0.);
+    assert_eq!(info.normalize_stick(113), 0.);
+    assert!(info.normalize_stick(143) > 0.);
+    assert!(info.normalize_stick(112) < 0.);
+
+    let empty = AbsInfo { minimum: 10, maximum: 10, flat: 0, ..info };
+    assert_eq!(empty.normalize_stick(10), 0.);
+  }
+
+  #[test]
+  fn normalize_trigger() {
+    let info = AbsInfo { value: 0, minimum: 0, maximum: 1023, fuzz: 0, flat: 0, resolution: 0 };
+
+    assert_eq!(info.normalize_trigger(0), 0.);
+    assert_eq!(info.normalize_trigger(1023), 1.);
+    assert_eq!(info.normalize_trigger(-5), 0.);
+    assert_eq!(info.normalize_trigger(2000), 1.);
+
+    let empty = AbsInfo { maximum: 0, ..info };
+    assert_eq!(empty.normalize_trigger(0), 0.);
+  }
+}
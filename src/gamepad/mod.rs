@@ -0,0 +1,272 @@
+//! Gamepads.
+//!
+//! glutin doesn’t handle gamepads, so they’re read from a separate backend: evdev on Linux (the
+//! `/dev/input/event*` nodes), nothing elsewhere yet. Any `GamepadBackend` can be plugged in, which
+//! is also how to feed fake gamepads to your input code.
+//!
+//! Buttons and axes follow a standard gamepad layout (the one of the Xbox controllers), whatever the
+//! actual device.
+
+#[cfg(target_os = "linux")]
+mod evdev;
+
+use std::collections::{HashMap, HashSet};
+
+use Action;
+
+#[cfg(target_os = "linux")]
+pub use self::evdev::EvdevBackend;
+
+/// Identifier of a connected gamepad.
+///
+/// Identifiers are attributed by the backend and never reused during its lifetime.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct GamepadId(pub u32);
+
+/// Gamepad button.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Button {
+  /// Bottom face button (A on Xbox controllers).
+  South,
+  /// Right face button (B on Xbox controllers).
+  East,
+  /// Top face button (Y on Xbox controllers).
+  North,
+  /// Left face button (X on Xbox controllers).
+  West,
+  LeftBumper,
+  RightBumper,
+  /// Left trigger, for gamepads reporting it as a button.
+  LeftTrigger,
+  /// Right trigger, for gamepads reporting it as a button.
+  RightTrigger,
+  Select,
+  Start,
+  /// Central button (Xbox or PlayStation logo).
+  Mode,
+  LeftThumb,
+  RightThumb,
+  DPadUp,
+  DPadDown,
+  DPadLeft,
+  DPadRight,
+}
+
+/// Gamepad axis.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Axis {
+  /// Left stick, from left (`-1`) to right (`1`).
+  LeftX,
+  /// Left stick, from up (`-1`) to down (`1`).
+  LeftY,
+  /// Right stick, from left (`-1`) to right (`1`).
+  RightX,
+  /// Right stick, from up (`-1`) to down (`1`).
+  RightY,
+  /// Left trigger, from released (`0`) to fully pressed (`1`).
+  LeftTrigger,
+  /// Right trigger, from released (`0`) to fully pressed (`1`).
+  RightTrigger,
+}
+
+/// Gamepad event.
+#[derive(Clone, Debug, PartialEq)]
+pub enum GamepadEvent {
+  /// A gamepad was plugged in (or was already there when the backend started).
+  Connected(GamepadId, String),
+  /// A gamepad was unplugged.
+  Disconnected(GamepadId),
+  /// A button was pressed or released.
+  Button(GamepadId, Button, Action),
+  /// An axis moved.
+  Axis(GamepadId, Axis, f32),
+}
+
+/// Source of gamepad events.
+pub trait GamepadBackend {
+  /// Push the events that happened since the last poll, including hotplug ones.
+  fn poll(&mut self, events: &mut Vec<GamepadEvent>);
+}
+
+/// Backend without any gamepad, used where no real backend exists.
+pub struct NullBackend;
+
+impl GamepadBackend for NullBackend {
+  fn poll(&mut self, _: &mut Vec<GamepadEvent>) {}
+}
+
+/// State of a connected gamepad.
+#[derive(Debug)]
+struct GamepadState {
+  name: String,
+  buttons: HashSet<Button>,
+  axes: HashMap<Axis, f32>,
+}
+
+/// Gamepads, polled along with `Device::events` once given to `GlutinDevice::set_gamepads`.
+///
+/// Like `InputState`, every poll starts a new frame: `Gamepads::events` only returns the events
+/// polled in that frame, while the button and axis queries reflect the current state.
+pub struct Gamepads {
+  backend: Box<GamepadBackend>,
+  gamepads: HashMap<GamepadId, GamepadState>,
+  events: Vec<GamepadEvent>,
+}
+
+impl Gamepads {
+  /// Gamepads from the default backend of the platform.
+  ///
+  /// That’s evdev on Linux; elsewhere, no gamepad is ever reported.
+  pub fn new() -> Self {
+    Self::with_backend(default_backend())
+  }
+
+  /// Gamepads from a given backend.
+  pub fn with_backend(backend: Box<GamepadBackend>) -> Self {
+    Gamepads {
+      backend,
+      gamepads: HashMap::new(),
+      events: Vec::new(),
+    }
+  }
+
+  /// Poll the backend and update the state of the gamepads.
+  pub fn poll(&mut self) {
+    self.events.clear();
+    self.backend.poll(&mut self.events);
+
+    for event in &self.events {
+      match *event {
+        GamepadEvent::Connected(id, ref name) => {
+          let state =
+            GamepadState {
+              name: name.clone(),
+              buttons: HashSet::new(),
+              axes: HashMap::new()
+            };
+
+          self.gamepads.insert(id, state);
+        }
+
+        GamepadEvent::Disconnected(id) => {
+          self.gamepads.remove(&id);
+        }
+
+        GamepadEvent::Button(id, button, action) => {
+          if let Some(state) = self.gamepads.get_mut(&id) {
+            match action {
+              Action::Pressed => { state.buttons.insert(button); }
+              Action::Released => { state.buttons.remove(&button); }
+            }
+          }
+        }
+
+        GamepadEvent::Axis(id, axis, value) => {
+          if let Some(state) = self.gamepads.get_mut(&id) {
+            state.axes.insert(axis, value);
+          }
+        }
+      }
+    }
+  }
+
+  /// Events polled during the last frame.
+  pub fn events(&self) -> &[GamepadEvent] {
+    &self.events
+  }
+
+  /// Connected gamepads.
+  pub fn connected<'a>(&'a self) -> Box<Iterator<Item = GamepadId> + 'a> {
+    Box::new(self.gamepads.keys().cloned())
+  }
+
+  /// Name of a connected gamepad.
+  pub fn name(&self, id: GamepadId) -> Option<&str> {
+    self.gamepads.get(&id).map(|state| state.name.as_str())
+  }
+
+  /// Whether a button of a connected gamepad is held down.
+  pub fn is_button_pressed(&self, id: GamepadId, button: Button) -> bool {
+    self.gamepads.get(&id).map_or(false, |state| state.buttons.contains(&button))
+  }
+
+  /// Value of an axis of a connected gamepad; `0` until it first moves.
+  pub fn axis(&self, id: GamepadId, axis: Axis) -> f32 {
+    self.gamepads.get(&id).and_then(|state| state.axes.get(&axis).cloned()).unwrap_or(0.)
+  }
+}
+
+impl Default for Gamepads {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+#[cfg(target_os = "linux")]
+fn default_backend() -> Box<GamepadBackend> {
+  Box::new(EvdevBackend::new())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn default_backend() -> Box<GamepadBackend> {
+  Box::new(NullBackend)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::VecDeque;
+
+  /// Backend returning a scripted list of events per poll.
+  struct ScriptedBackend {
+    polls: VecDeque<Vec<GamepadEvent>>,
+  }
+
+  impl GamepadBackend for ScriptedBackend {
+    fn poll(&mut self, events: &mut Vec<GamepadEvent>) {
+      events.extend(self.polls.pop_front().unwrap_or_default());
+    }
+  }
+
+  #[test]
+  fn scripted_backend() {
+    let id = GamepadId(3);
+    let polls =
+      vec![
+        vec![GamepadEvent::Connected(id, "pad".to_owned())],
+        vec![
+          GamepadEvent::Button(id, Button::South, Action::Pressed),
+          GamepadEvent::Button(id, Button::Start, Action::Pressed),
+          GamepadEvent::Axis(id, Axis::LeftX, -0.5),
+        ],
+        vec![GamepadEvent::Button(id, Button::South, Action::Released)],
+        vec![GamepadEvent::Disconnected(id)],
+      ];
+    let mut gamepads = Gamepads::with_backend(Box::new(ScriptedBackend { polls: polls.into_iter().collect() }));
+
+    gamepads.poll();
+    assert_eq!(gamepads.connected().collect::<Vec<_>>(), vec![id]);
+    assert_eq!(gamepads.name(id), Some("pad"));
+    assert_eq!(gamepads.axis(id, Axis::LeftX), 0.);
+
+    gamepads.poll();
+    assert_eq!(gamepads.events().len(), 3);
+    assert!(gamepads.is_button_pressed(id, Button::South));
+    assert!(gamepads.is_button_pressed(id, Button::Start));
+    assert_eq!(gamepads.axis(id, Axis::LeftX), -0.5);
+
+    gamepads.poll();
+    assert_eq!(gamepads.events(), &[GamepadEvent::Button(id, Button::South, Action::Released)][..]);
+    assert!(!gamepads.is_button_pressed(id, Button::South));
+    assert!(gamepads.is_button_pressed(id, Button::Start));
+
+    gamepads.poll();
+    assert_eq!(gamepads.connected().count(), 0);
+    assert_eq!(gamepads.name(id), None);
+    assert!(!gamepads.is_button_pressed(id, Button::Start));
+    assert_eq!(gamepads.axis(id, Axis::LeftX), 0.);
+
+    gamepads.poll();
+    assert!(gamepads.events().is_empty());
+  }
+}
@@ -0,0 +1,118 @@
+//! Headless device.
+
+use glutin;
+use glutin::GlContext as GlContextTrait;
+use luminance_windowing::{Device, WindowDim, WindowOpt};
+use std::iter;
+
+use builder::load_gl;
+use capture::{self, Screenshot};
+use {ContextOpt, CreationError, DeviceError, Event};
+
+/// Headless device object.
+///
+/// It has an OpenGL context but no window, and renders to an offscreen default framebuffer. On
+/// Linux, it uses OSMesa, a software rasterizer that needs neither a GPU nor a display server,
+/// which makes it a good fit to run render tests in CI.
+///
+/// It implements `Device` like `GlutinDevice` does, but never yields any event. As there is no
+/// monitor, `WindowDim::Fullscreen` is rejected.
+pub struct GlutinHeadlessDevice {
+  /// Headless context.
+  context: glutin::HeadlessContext,
+  /// Size of the default framebuffer.
+  size: [u32; 2],
+  /// Version of the context that was actually obtained.
+  gl_version: Option<(u8, u8)>,
+}
+
+impl GlutinHeadlessDevice {
+  /// Create a headless device with a specific OpenGL context configuration.
+  ///
+  /// `Device::new` uses `ContextOpt::default()`.
+  pub fn new_with_context_opt(w: u32, h: u32, ctx_opt: ContextOpt) -> Result<Self, DeviceError> {
+    let context = create_headless_context(w, h, &ctx_opt).map_err(DeviceError::CreationError)?;
+    let gl_version = load_gl(&context, &ctx_opt)?;
+
+    let device =
+      GlutinHeadlessDevice {
+        context,
+        size: [w, h],
+        gl_version
+      };
+
+    Ok(device)
+  }
+
+  /// Version of the OpenGL context that was actually obtained, if the driver reported it.
+  pub fn gl_version(&self) -> Option<(u8, u8)> {
+    self.gl_version
+  }
+
+  /// Draw like `Device::draw` does, and read the default framebuffer back.
+  pub fn draw_and_capture<F>(&mut self, f: F) -> Screenshot where F: FnOnce() {
+    let [w, h] = self.size;
+    let mut screenshot = None;
+
+    self.draw(|| {
+      f();
+      screenshot = Some(capture::read_default_framebuffer(w, h));
+    });
+
+    screenshot.expect("draw didn’t call its closure")
+  }
+}
+
+impl Device for GlutinHeadlessDevice {
+  type Event = Event;
+
+  type Error = DeviceError;
+
+  fn new(
+    dim: WindowDim,
+    _: &str,
+    _: WindowOpt
+  ) -> Result<Self, Self::Error> {
+    match dim {
+      WindowDim::Windowed(w, h) | WindowDim::FullscreenRestricted(w, h) =>
+        Self::new_with_context_opt(w, h, ContextOpt::default()),
+      WindowDim::Fullscreen => Err(DeviceError::NoMonitor)
+    }
+  }
+
+  fn size(&self) -> [u32; 2] {
+    self.size
+  }
+
+  fn events<'a>(&'a mut self) -> Box<Iterator<Item = Self::Event> + 'a> {
+    Box::new(iter::empty())
+  }
+
+  fn draw<F>(&mut self, f: F) where F: FnOnce() {
+    f();
+    let _ = self.context.swap_buffers();
+  }
+}
+
+/// Create the headless context, trying each requested version in turn.
+fn create_headless_context(
+  w: u32,
+  h: u32,
+  ctx_opt: &ContextOpt
+) -> Result<glutin::HeadlessContext, CreationError> {
+  let mut last_err = CreationError::OpenGlVersionNotSupported;
+
+  for gl_request in ctx_opt.gl_requests() {
+    let builder =
+      glutin::HeadlessRendererBuilder::new(w, h)
+        .with_gl(gl_request)
+        .with_gl_profile(ctx_opt.profile());
+
+    match builder.build() {
+      Ok(context) => return Ok(context),
+      Err(e) => last_err = e
+    }
+  }
+
+  Err(last_err)
+}
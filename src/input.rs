@@ -0,0 +1,210 @@
+//! Typed input streams.
+//!
+//! Raw events are decoded into per-category channels while being polled. A channel is only fed
+//! once its receiver has been asked for, so that unused streams don’t pile up events forever.
+
+use glutin::{self, ModifiersState, MouseButton, MouseScrollDelta, WindowEvent};
+use std::collections::HashSet;
+use std::sync::mpsc::{Receiver, Sender, channel};
+
+use {Action, Event, Key};
+
+/// Key press or release.
+#[derive(Clone, Copy, Debug)]
+pub struct KeyEvent {
+  /// Platform-dependent code of the physical key, independent of the keyboard layout.
+  ///
+  /// Use it for bindings that depend on the position of the keys, such as WASD.
+  pub scancode: u32,
+  /// Key, as mapped by the keyboard layout, if it has a virtual key code.
+  pub key: Option<Key>,
+  /// Whether the key was pressed or released.
+  pub action: Action,
+  /// State of the modifiers (shift, ctrl, alt, logo) when the event happened.
+  pub modifiers: ModifiersState,
+  /// Whether this press was generated by the key being held down.
+  pub repeat: bool,
+}
+
+/// A lazily created channel.
+struct Stream<T> {
+  channel: Option<(Sender<T>, Receiver<T>)>,
+}
+
+impl<T> Stream<T> {
+  fn new() -> Self {
+    Stream { channel: None }
+  }
+
+  /// Send a value if somebody is listening.
+  fn send(&self, value: T) {
+    if let Some((ref sx, _)) = self.channel {
+      // we own the receiver, so this cannot fail
+      let _ = sx.send(value);
+    }
+  }
+
+  /// Get the receiver, creating the channel if needed.
+  fn receiver(&mut self) -> &Receiver<T> {
+    &self.channel.get_or_insert_with(channel).1
+  }
+}
+
+/// Keyboard, mouse button, cursor and scroll streams.
+pub(crate) struct InputStreams {
+  keyboard: Stream<KeyEvent>,
+  mouse: Stream<(MouseButton, Action)>,
+  mouse_move: Stream<[f32; 2]>,
+  scroll: Stream<[f32; 2]>,
+  /// Scancodes of the keys held down, to detect repeats.
+  held: HashSet<u32>,
+}
+
+impl InputStreams {
+  pub(crate) fn new() -> Self {
+    InputStreams {
+      keyboard: Stream::new(),
+      mouse: Stream::new(),
+      mouse_move: Stream::new(),
+      scroll: Stream::new(),
+      held: HashSet::new(),
+    }
+  }
+
+  pub(crate) fn keyboard(&mut self) -> &Receiver<KeyEvent> {
+    self.keyboard.receiver()
+  }
+
+  pub(crate) fn mouse(&mut self) -> &Receiver<(MouseButton, Action)> {
+    self.mouse.receiver()
+  }
+
+  pub(crate) fn mouse_move(&mut self) -> &Receiver<[f32; 2]> {
+    self.mouse_move.receiver()
+  }
+
+  pub(crate) fn scroll(&mut self) -> &Receiver<[f32; 2]> {
+    self.scroll.receiver()
+  }
+
+  /// Decode an event and send it to the matching stream, if any.
+  pub(crate) fn dispatch(&mut self, event: &Event) {
+    let event =
+      match *event {
+        Event::WindowEvent { ref event, .. } => event,
+        _ => return
+      };
+
+    match *event {
+      WindowEvent::KeyboardInput { input: glutin::KeyboardInput { scancode, state, virtual_keycode, modifiers }, .. } => {
+        let key_event = self.key_event(scancode, virtual_keycode, state, modifiers);
+        self.keyboard.send(key_event);
+      }
+
+      // we won’t get the release events of keys held while the window is unfocused
+      WindowEvent::Focused(false) => {
+        self.focus_lost();
+      }
+
+      WindowEvent::MouseInput { state, button, .. } => {
+        self.mouse.send((button, state));
+      }
+
+      WindowEvent::CursorMoved { position: (x, y), .. } => {
+        self.mouse_move.send([x as f32, y as f32]);
+      }
+
+      WindowEvent::MouseWheel { delta, .. } => {
+        self.scroll.send(scroll_delta(delta));
+      }
+
+      _ => ()
+    }
+  }
+
+  /// Forget the held keys, whose release we won’t get.
+  fn focus_lost(&mut self) {
+    self.held.clear();
+  }
+
+  /// Build the event of a key press or release, detecting repeats.
+  fn key_event(&mut self, scancode: u32, key: Option<Key>, action: Action, modifiers: ModifiersState) -> KeyEvent {
+    let repeat =
+      match action {
+        Action::Pressed => !self.held.insert(scancode),
+        Action::Released => {
+          self.held.remove(&scancode);
+          false
+        }
+      };
+
+    KeyEvent { scancode, key, action, modifiers, repeat }
+  }
+}
+
+/// Scroll delta, in lines or in pixels depending on the input device.
+pub(crate) fn scroll_delta(delta: MouseScrollDelta) -> [f32; 2] {
+  match delta {
+    MouseScrollDelta::LineDelta(x, y) => [x, y],
+    MouseScrollDelta::PixelDelta(x, y) => [x, y]
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Fields of a key event, as `ModifiersState` is not comparable with every glutin 0.12.x.
+  fn key_event(streams: &mut InputStreams, scancode: u32, key: Option<Key>, action: Action) -> (u32, Option<Key>, Action, bool) {
+    let event = streams.key_event(scancode, key, action, ModifiersState::default());
+    (event.scancode, event.key, event.action, event.repeat)
+  }
+
+  #[test]
+  fn key_repeats() {
+    let mut streams = InputStreams::new();
+
+    assert_eq!(key_event(&mut streams, 30, Some(Key::A), Action::Pressed), (30, Some(Key::A), Action::Pressed, false));
+    assert_eq!(key_event(&mut streams, 30, Some(Key::A), Action::Pressed), (30, Some(Key::A), Action::Pressed, true));
+    assert_eq!(key_event(&mut streams, 30, Some(Key::A), Action::Released), (30, Some(Key::A), Action::Released, false));
+    assert_eq!(key_event(&mut streams, 30, Some(Key::A), Action::Pressed), (30, Some(Key::A), Action::Pressed, false));
+  }
+
+  #[test]
+  fn key_focus_lost() {
+    let mut streams = InputStreams::new();
+
+    // the release happened while unfocused, so the next press is not a repeat
+    key_event(&mut streams, 30, Some(Key::A), Action::Pressed);
+    streams.focus_lost();
+    assert!(!key_event(&mut streams, 30, Some(Key::A), Action::Pressed).3);
+  }
+
+  #[test]
+  fn key_scancode_only() {
+    let mut streams = InputStreams::new();
+
+    assert_eq!(key_event(&mut streams, 0x1d0, None, Action::Pressed), (0x1d0, None, Action::Pressed, false));
+    assert!(key_event(&mut streams, 0x1d0, None, Action::Pressed).3);
+  }
+
+  #[test]
+  fn stream_only_fed_once_asked_for() {
+    let mut stream = Stream::new();
+
+    // nobody listens yet: the value is dropped
+    stream.send(1);
+    stream.send(2);
+    assert!(stream.receiver().try_recv().is_err());
+
+    stream.send(3);
+    stream.send(4);
+    assert_eq!(stream.receiver().try_iter().collect::<Vec<_>>(), vec![3, 4]);
+  }
+
+  #[test]
+  fn scroll_deltas() {
+    assert_eq!(scroll_delta(MouseScrollDelta::LineDelta(1., -2.)), [1., -2.]);
+    assert_eq!(scroll_delta(MouseScrollDelta::PixelDelta(0.5, 12.)), [0.5, 12.]);
+  }
+}
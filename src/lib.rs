@@ -2,39 +2,576 @@ extern crate gl;
 extern crate glutin;
 extern crate luminance;
 extern crate luminance_windowing;
+#[cfg(target_os = "linux")]
+extern crate libc;
+
+mod builder;
+mod capture;
+mod error;
+mod framebuffer;
+mod gamepad;
+mod headless;
+mod input;
+mod limiter;
+mod monitor;
+mod record;
+mod runner;
+mod state;
+mod stats;
+mod text;
+mod vsync;
 
 use glutin::GlContext as GlContextTrait;
-pub use glutin::{CreationError, ElementState, Event, MouseButton, VirtualKeyCode};
+pub use builder::GlutinDeviceBuilder;
+pub use capture::Screenshot;
+pub use error::DeviceError;
+pub use framebuffer::FramebufferOpt;
+pub use gamepad::{Axis, Button, GamepadBackend, GamepadEvent, GamepadId, Gamepads, NullBackend};
+#[cfg(target_os = "linux")]
+pub use gamepad::EvdevBackend;
+pub use headless::GlutinHeadlessDevice;
+pub use input::KeyEvent;
+pub use monitor::Monitor;
+pub use record::RecordSink;
+pub use runner::{App, Loop, Runner};
+pub use state::InputState;
+pub use stats::FrameStats;
+pub use vsync::Vsync;
+pub use glutin::{Api, CreationError, ElementState, Event, GlProfile, ModifiersState, MouseButton, MouseCursor,
+                 PixelFormat, VirtualKeyCode};
 pub use luminance_windowing::{Device, WindowDim, WindowOpt};
 
-use std::os::raw::c_void;
-use std::sync::mpsc::{Receiver, channel};
-use std::thread::{JoinHandle, spawn};
+use std::sync::mpsc::Receiver;
+
+use input::InputStreams;
+use limiter::FrameLimiter;
+use record::Recorder;
+use stats::GpuTimer;
+use text::TextInput;
+use std::io;
+use std::time::{Duration, Instant};
 
 pub type Key = VirtualKeyCode;
 pub type Action = ElementState;
-pub type Keyboard = Receiver<(VirtualKeyCode, ElementState)>;
+/// Stream of key presses and releases.
+pub type Keyboard = Receiver<KeyEvent>;
+/// Stream of mouse button presses and releases.
 pub type Mouse = Receiver<(MouseButton, ElementState)>;
+/// Stream of cursor positions, in pixels, relative to the top-left corner of the window.
 pub type MouseMove = Receiver<[f32; 2]>;
+/// Stream of scroll deltas, in lines or in pixels depending on the input device.
 pub type Scroll = Receiver<[f32; 2]>;
 
-/// Error that can be risen while creating a `Device` object.
-#[derive(Debug)]
-pub enum DeviceError {
-  CreationError(CreationError)
+/// OpenGL context options.
+///
+/// Select the API, version and profile of the context to create. Several versions can be given, in
+/// which case they are tried in order until one of them succeeds. If no version is given at all,
+/// the latest version supported by the driver is requested.
+#[derive(Clone, Debug)]
+pub struct ContextOpt {
+  api: Api,
+  versions: Vec<(u8, u8)>,
+  profile: GlProfile,
+  vsync: Option<Vsync>,
+}
+
+impl ContextOpt {
+  /// Set the API to use.
+  pub fn with_api(self, api: Api) -> Self {
+    ContextOpt { api, ..self }
+  }
+
+  /// Request a single version of the API.
+  pub fn with_version(self, major: u8, minor: u8) -> Self {
+    ContextOpt { versions: vec![(major, minor)], ..self }
+  }
+
+  /// Request several versions of the API, tried in order (e.g. `[(4, 6), (4, 5), (3, 3)]`).
+  pub fn with_versions<V>(self, versions: V) -> Self where V: IntoIterator<Item = (u8, u8)> {
+    ContextOpt { versions: versions.into_iter().collect(), ..self }
+  }
+
+  /// Set the profile to use.
+  pub fn with_profile(self, profile: GlProfile) -> Self {
+    ContextOpt { profile, ..self }
+  }
+
+  /// Set the vertical synchronization mode.
+  ///
+  /// If no mode is set, the driver’s default swap interval is left alone.
+  pub fn with_vsync(self, vsync: Vsync) -> Self {
+    ContextOpt { vsync: Some(vsync), ..self }
+  }
+
+  /// API to use.
+  pub fn api(&self) -> Api {
+    self.api
+  }
+
+  /// Versions to try, in order.
+  pub fn versions(&self) -> &[(u8, u8)] {
+    &self.versions
+  }
+
+  /// Profile to use.
+  pub fn profile(&self) -> GlProfile {
+    self.profile
+  }
+
+  /// Vertical synchronization mode, if one was set.
+  pub fn vsync(&self) -> Option<Vsync> {
+    self.vsync
+  }
+
+  /// Requests to pass to glutin, in order.
+  fn gl_requests(&self) -> Vec<glutin::GlRequest> {
+    if self.versions.is_empty() {
+      vec![glutin::GlRequest::Latest]
+    } else {
+      self.versions.iter().map(|&version| glutin::GlRequest::Specific(self.api, version)).collect()
+    }
+  }
+}
+
+impl Default for ContextOpt {
+  /// OpenGL 3.3 core profile, with the driver’s default vsync.
+  fn default() -> Self {
+    ContextOpt {
+      api: Api::OpenGl,
+      versions: vec![(3, 3)],
+      profile: GlProfile::Core,
+      vsync: None,
+    }
+  }
 }
 
 /// Device object.
 ///
 /// Upon window and context creation, this type is used to add interaction and context handling.
+///
+/// The window and its events live on the thread that created the device: events are pumped from
+/// the windowing system every time `Device::events` is called, so that’s the thread you must use
+/// the device on.
+///
+/// Dropping the device destroys the window, its context and the events loop; no thread or other
+/// resource outlives it.
 pub struct GlutinDevice {
-  /// Event receiver.
-  events_rx: Receiver<Event>,
-  /// Window.
+  /// Window. Declared before the events loop so that it gets dropped first.
   window: glutin::GlWindow,
-  /// Event thread join handle. Unused and keep around until death.
-  #[allow(dead_code)]
-  event_thread: JoinHandle<()>,
+  /// Events loop, polled in `Device::events`.
+  events_loop: glutin::EventsLoop,
+  /// Version of the context that was actually obtained.
+  gl_version: Option<(u8, u8)>,
+  /// Effective vertical synchronization mode, if known.
+  vsync: Option<Vsync>,
+  /// Pixel format of the default framebuffer.
+  pixel_format: PixelFormat,
+  /// Whether the window was closed, either by the user or with `GlutinDevice::close`.
+  closed: bool,
+  /// Typed input streams.
+  input_streams: InputStreams,
+  /// Input state snapshot.
+  input_state: InputState,
+  /// New size of the framebuffer if it was resized during the last poll.
+  resized: Option<[u32; 2]>,
+  /// New HiDPI factor if it changed during the last poll.
+  hidpi_factor_changed: Option<f32>,
+  /// Frame recorder, if recording.
+  recorder: Option<Recorder>,
+  /// Error that stopped the recording, if any.
+  record_error: Option<io::Error>,
+  /// Frame timing statistics.
+  frame_stats: FrameStats,
+  /// GPU timer, if GPU timing is enabled.
+  gpu_timer: Option<GpuTimer>,
+  /// Frame rate limiter.
+  limiter: FrameLimiter,
+  /// Minimum frame period while focused.
+  frame_period: Option<Duration>,
+  /// Minimum frame period while unfocused.
+  unfocused_frame_period: Option<Duration>,
+  /// Whether the window has the focus.
+  focused: bool,
+  /// Cursor icon, when visible.
+  cursor_icon: MouseCursor,
+  /// Whether the cursor is hidden.
+  cursor_hidden: bool,
+  /// Whether the cursor is grabbed.
+  cursor_grabbed: bool,
+  /// Gamepads, polled along with the events.
+  gamepads: Option<Gamepads>,
+  /// Text input.
+  text_input: TextInput,
+}
+
+impl GlutinDevice {
+  /// Close the window.
+  ///
+  /// The window is hidden right away and `is_closed` returns `true` from now on. The window and its
+  /// context are destroyed when the device is dropped.
+  pub fn close(&mut self) {
+    self.window.hide();
+    self.closed = true;
+  }
+
+  /// Whether the window was closed, either by the user or with `close`.
+  pub fn is_closed(&self) -> bool {
+    self.closed
+  }
+
+  /// Version of the OpenGL context that was actually obtained, if the driver reported it.
+  pub fn gl_version(&self) -> Option<(u8, u8)> {
+    self.gl_version
+  }
+
+  /// Change the title of the window.
+  pub fn set_title(&self, title: &str) {
+    self.window.set_title(title);
+  }
+
+  /// Resize the window, in the same unit as `WindowDim`.
+  pub fn set_size(&self, w: u32, h: u32) {
+    self.window.set_inner_size(w, h);
+  }
+
+  /// Position of the top-left corner of the window on the desktop, if known.
+  pub fn position(&self) -> Option<[i32; 2]> {
+    self.window.get_position().map(|(x, y)| [x, y])
+  }
+
+  /// Move the top-left corner of the window on the desktop.
+  pub fn set_position(&self, x: i32, y: i32) {
+    self.window.set_position(x, y);
+  }
+
+  /// Switch between windowed and fullscreen.
+  ///
+  /// Fullscreen uses the monitor the window is currently on. Like at creation, it’s borderless and
+  /// doesn’t change the video mode of the monitor (see `GlutinDeviceBuilder::with_dim`).
+  pub fn set_fullscreen(&self, dim: WindowDim) {
+    self.apply_dim(dim, self.window.get_current_monitor());
+  }
+
+  /// Switch between windowed and fullscreen on a given monitor.
+  pub fn set_fullscreen_on(&self, dim: WindowDim, monitor: &Monitor) -> Result<(), DeviceError> {
+    let monitor = monitor::monitor_id(&self.events_loop, monitor).ok_or(DeviceError::NoMonitor)?;
+    self.apply_dim(dim, monitor);
+
+    Ok(())
+  }
+
+  /// Enumerate the available monitors.
+  pub fn monitors(&self) -> Vec<Monitor> {
+    monitor::monitors(&self.events_loop)
+  }
+
+  fn apply_dim(&self, dim: WindowDim, monitor: glutin::MonitorId) {
+    match dim {
+      WindowDim::Windowed(w, h) => {
+        self.window.set_fullscreen(None);
+        self.window.set_inner_size(w, h);
+      }
+
+      WindowDim::Fullscreen => {
+        self.window.set_fullscreen(Some(monitor));
+      }
+
+      WindowDim::FullscreenRestricted(w, h) => {
+        self.window.set_inner_size(w, h);
+        self.window.set_fullscreen(Some(monitor));
+      }
+    }
+  }
+
+  /// Maximize or unmaximize the window.
+  ///
+  /// There is no way to minimize the window, as glutin 0.12 doesn’t support it.
+  pub fn set_maximized(&self, maximized: bool) {
+    self.window.set_maximized(maximized);
+  }
+
+  /// Show the window.
+  pub fn show(&self) {
+    self.window.show();
+  }
+
+  /// Hide the window.
+  pub fn hide(&self) {
+    self.window.hide();
+  }
+
+  /// Show or hide the cursor when it’s over the window.
+  pub fn set_cursor_visible(&mut self, visible: bool) {
+    self.cursor_hidden = !visible;
+    self.window.set_cursor(if visible { self.cursor_icon } else { MouseCursor::NoneCursor });
+  }
+
+  /// Whether the cursor is visible when it’s over the window.
+  pub fn is_cursor_visible(&self) -> bool {
+    !self.cursor_hidden
+  }
+
+  /// Change the icon of the cursor.
+  ///
+  /// If the cursor is hidden, the icon is used when it’s shown again. Custom cursor images are not
+  /// supported.
+  pub fn set_cursor_icon(&mut self, icon: MouseCursor) {
+    self.cursor_icon = icon;
+
+    if !self.cursor_hidden {
+      self.window.set_cursor(icon);
+    }
+  }
+
+  /// Grab the cursor, confining it to the window, or release it.
+  ///
+  /// That’s what you want for a first-person camera, along with `GlutinDevice::set_cursor_visible`.
+  pub fn set_cursor_grabbed(&mut self, grabbed: bool) -> Result<(), DeviceError> {
+    let state = if grabbed { glutin::CursorState::Grab } else { glutin::CursorState::Normal };
+    self.window.set_cursor_state(state).map_err(DeviceError::CursorError)?;
+    self.cursor_grabbed = grabbed;
+
+    Ok(())
+  }
+
+  /// Whether the cursor is grabbed.
+  pub fn is_cursor_grabbed(&self) -> bool {
+    self.cursor_grabbed
+  }
+
+  /// Move the cursor, in pixels, relative to the top-left corner of the window.
+  pub fn set_cursor_position(&self, x: i32, y: i32) -> Result<(), DeviceError> {
+    self.window.set_cursor_position(x, y)
+      .map_err(|_| DeviceError::CursorError("cannot set the cursor position".to_owned()))
+  }
+
+  /// Keyboard stream.
+  ///
+  /// Like the other input streams, it’s fed while events are polled with `Device::events`, and only
+  /// once it has been asked for.
+  pub fn keyboard(&mut self) -> &Keyboard {
+    self.input_streams.keyboard()
+  }
+
+  /// Mouse button stream.
+  pub fn mouse(&mut self) -> &Mouse {
+    self.input_streams.mouse()
+  }
+
+  /// Cursor position stream.
+  pub fn mouse_move(&mut self) -> &MouseMove {
+    self.input_streams.mouse_move()
+  }
+
+  /// Scroll stream.
+  pub fn scroll(&mut self) -> &Scroll {
+    self.input_streams.scroll()
+  }
+
+  /// Ratio between physical pixels and logical units of the monitor the window is on.
+  pub fn hidpi_factor(&self) -> f32 {
+    self.window.hidpi_factor()
+  }
+
+  /// New HiDPI factor, if it changed during the last poll (e.g. the window moved to another
+  /// monitor).
+  pub fn hidpi_factor_changed(&self) -> Option<f32> {
+    self.hidpi_factor_changed
+  }
+
+  /// Size of the default framebuffer, in physical pixels.
+  ///
+  /// This is what you want for viewports. This is also what `Device::size` returns.
+  pub fn framebuffer_size(&self) -> [u32; 2] {
+    let size = self.window.get_inner_size().unwrap_or((0, 0));
+    to_physical_size(size, self.hidpi_factor())
+  }
+
+  /// Size of the window, in logical units.
+  ///
+  /// This is what you want to lay out UI elements so that they keep the same size on HiDPI
+  /// monitors.
+  pub fn window_size(&self) -> [f32; 2] {
+    let [w, h] = self.framebuffer_size();
+    let factor = self.hidpi_factor();
+
+    [w as f32 / factor, h as f32 / factor]
+  }
+
+  /// New size of the default framebuffer, in physical pixels, if it was resized during the last
+  /// poll.
+  ///
+  /// The context is resized automatically; use this to rebuild whatever depends on the size of the
+  /// default framebuffer, such as luminance’s `Framebuffer::back_buffer`.
+  pub fn framebuffer_resized(&self) -> Option<[u32; 2]> {
+    self.resized
+  }
+
+  /// Draw like `Device::draw` does, and read the back buffer back before it gets swapped.
+  ///
+  /// This stalls the pipeline until the frame is rendered, so don’t do it every frame.
+  pub fn draw_and_capture<F>(&mut self, f: F) -> Screenshot where F: FnOnce() {
+    let [w, h] = self.framebuffer_size();
+    let mut screenshot = None;
+
+    self.draw(|| {
+      f();
+      screenshot = Some(capture::read_default_framebuffer(w, h));
+    });
+
+    screenshot.expect("draw didn’t call its closure")
+  }
+
+  /// Start recording every frame presented by `Device::draw` to a sink.
+  ///
+  /// Frames are read back asynchronously and written a few frames later; `stop_recording` writes
+  /// the remaining ones. If a recording is already running, it’s stopped first; call
+  /// `stop_recording` yourself beforehand to know whether the previous recording succeeded, as its
+  /// errors are discarded here.
+  pub fn start_recording(&mut self, sink: RecordSink) -> io::Result<()> {
+    let _ = self.stop_recording();
+    self.recorder = Some(Recorder::new(sink)?);
+
+    Ok(())
+  }
+
+  /// Stop recording, writing the frames that are still pending.
+  ///
+  /// If writing a frame failed while drawing, the recording was stopped at that point and the error
+  /// is returned here.
+  pub fn stop_recording(&mut self) -> io::Result<()> {
+    if let Some(e) = self.record_error.take() {
+      return Err(e);
+    }
+
+    match self.recorder.take() {
+      Some(recorder) => recorder.finish(),
+      None => Ok(())
+    }
+  }
+
+  /// Whether frames are being recorded.
+  pub fn is_recording(&self) -> bool {
+    self.recorder.is_some()
+  }
+
+  /// Number of frames written since the recording started.
+  pub fn recorded_frames(&self) -> u64 {
+    self.recorder.as_ref().map_or(0, Recorder::frame_count)
+  }
+
+  /// Frame timing statistics, as of the last `Device::draw`.
+  pub fn frame_stats(&self) -> &FrameStats {
+    &self.frame_stats
+  }
+
+  /// Enable or disable GPU timing of the `Device::draw` closures.
+  ///
+  /// This requires `GL_TIME_ELAPSED` queries (OpenGL 3.3 or `ARB_timer_query`; OpenGL ES is not
+  /// supported). Results are available with `FrameStats::gpu_time`. Returns `true` if GPU timing
+  /// is now in the requested state, `false` if it couldn’t be enabled.
+  pub fn set_gpu_timing(&mut self, enabled: bool) -> bool {
+    if enabled {
+      if self.gpu_timer.is_none() {
+        self.gpu_timer = GpuTimer::new();
+      }
+
+      self.gpu_timer.is_some()
+    } else {
+      if let Some(gpu_timer) = self.gpu_timer.take() {
+        gpu_timer.delete();
+        self.frame_stats.clear_gpu_time();
+      }
+
+      true
+    }
+  }
+
+  /// Limit the frame rate, or remove the limit with `None`.
+  ///
+  /// `Device::draw` waits after swapping buffers so that frames are not presented faster than that.
+  /// This is useful when vsync is off or ignored by the driver.
+  pub fn set_max_fps(&mut self, fps: Option<f64>) {
+    self.frame_period = fps.and_then(limiter::fps_to_period);
+  }
+
+  /// Limit the frame rate while the window doesn’t have the focus, or remove that limit with `None`.
+  ///
+  /// Use a low value (e.g. `Some(5.)`) to idle in the background. When not set, the limit set with
+  /// `set_max_fps` applies.
+  pub fn set_unfocused_max_fps(&mut self, fps: Option<f64>) {
+    self.unfocused_frame_period = fps.and_then(limiter::fps_to_period);
+  }
+
+  /// Whether the window has the focus.
+  pub fn is_focused(&self) -> bool {
+    self.focused
+  }
+
+  /// Enable or disable text input.
+  ///
+  /// Turn it on while a text field has the focus: `text` then returns what was typed, as composed
+  /// by the platform (dead keys, input methods). Editing keys such as backspace or return are not
+  /// text; get them from the keyboard.
+  ///
+  /// The position of the input method’s candidate window can’t be set, as glutin 0.12 doesn’t
+  /// support it: the platform places it wherever it sees fit.
+  pub fn set_text_input(&mut self, enabled: bool) {
+    self.text_input.set_enabled(enabled);
+  }
+
+  /// Whether text input is enabled.
+  pub fn is_text_input_enabled(&self) -> bool {
+    self.text_input.is_enabled()
+  }
+
+  /// Text typed during the last frame, if text input is enabled.
+  pub fn text(&self) -> &str {
+    self.text_input.text()
+  }
+
+  /// Set the gamepads to poll along with the events, or stop polling them with `None`.
+  pub fn set_gamepads(&mut self, gamepads: Option<Gamepads>) {
+    self.gamepads = gamepads;
+  }
+
+  /// Gamepads, as of the last time events were polled.
+  pub fn gamepads(&self) -> Option<&Gamepads> {
+    self.gamepads.as_ref()
+  }
+
+  /// Input state, as of the last time events were polled.
+  pub fn input(&self) -> &InputState {
+    &self.input_state
+  }
+
+  /// Pixel format of the default framebuffer, as chosen by the platform.
+  pub fn pixel_format(&self) -> &PixelFormat {
+    &self.pixel_format
+  }
+
+  /// Effective vertical synchronization mode.
+  ///
+  /// `None` if no mode was requested, in which case the driver’s default applies, or if the
+  /// requested mode couldn’t be set. On macOS and EGL, where only glutin can set it at creation,
+  /// this is the mode glutin was asked for (adaptive vsync becoming regular vsync), unverified.
+  pub fn vsync(&self) -> Option<Vsync> {
+    self.vsync
+  }
+
+  /// Change the vertical synchronization mode.
+  ///
+  /// This is not supported on every platform (e.g. macOS, or EGL). Returns `true` if the new mode
+  /// was applied; otherwise, the previous mode stays in effect.
+  pub fn set_vsync(&mut self, vsync: Vsync) -> bool {
+    let applied = vsync::set_swap_interval(&self.window, vsync) == Some(true);
+
+    if applied {
+      self.vsync = Some(vsync);
+    }
+
+    applied
+  }
 }
 
 impl Device for GlutinDevice {
@@ -47,79 +584,124 @@ impl Device for GlutinDevice {
     title: &str, 
     win_opt: WindowOpt
   ) -> Result<Self, Self::Error> {
-    // OpenGL hints
-    let gl_version = glutin::GlRequest::Specific(glutin::Api::OpenGl, (3, 3));
-    let gl_profile = glutin::GlProfile::Core;
-
-    let events_loop = glutin::EventsLoop::new();
-
-    // create the OpenGL window by creating a window, a context and attaching it to the window
-    let window =
-      glutin::WindowBuilder::new()
-        .with_title(title);
-
-    let window =
-      match dim {
-        WindowDim::Windowed(w, h) => window.with_dimensions(w, h),
-        WindowDim::Fullscreen => window.with_fullscreen(None),
-        WindowDim::FullscreenRestricted(w, h) => window.with_dimensions(w, h).with_fullscreen(None)
-      };
+    GlutinDeviceBuilder::new()
+      .with_dim(dim)
+      .with_title(title)
+      .with_window_opt(win_opt)
+      .build()
+  }
 
+  fn size(&self) -> [u32; 2] {
+    self.framebuffer_size()
+  }
 
-    let ctx = 
-      glutin::ContextBuilder::new()
-        .with_gl(gl_version)
-        .with_gl_profile(gl_profile);
+  fn events<'a>(&'a mut self) -> Box<Iterator<Item = Self::Event> + 'a> {
+    let mut events = Vec::new();
+    self.events_loop.poll_events(|event| events.push(event));
 
-    let gl_window =
-      glutin::GlWindow::new(window, ctx, &events_loop).map_err(DeviceError::CreationError)?;
+    self.input_state.new_frame();
+    self.text_input.new_frame();
+    self.resized = None;
+    self.hidpi_factor_changed = None;
 
-    if win_opt.is_cursor_hidden() {
-      gl_window.set_cursor(glutin::MouseCursor::NoneCursor);
-    } else {
-      gl_window.set_cursor(glutin::MouseCursor::Default);
+    if let Some(ref mut gamepads) = self.gamepads {
+      gamepads.poll();
     }
 
-    unsafe { gl_window.make_current().unwrap() };
-     gl::load_with(|s| gl_window.get_proc_address(s) as *const c_void);
+    for event in &events {
+      match *event {
+        Event::WindowEvent { event: glutin::WindowEvent::Closed, .. } => {
+          self.closed = true;
+        }
 
-    // place the event loop in a thread; every time an event is polled from glutin,
-    // enqueue it in a channel so that we can get it back in the device
-    let (events_sx, events_rx) = channel();
-    let event_thread = spawn(move || {
-      events_loop.run_forever(|event| {
-        events_sx.send(event);
+        Event::WindowEvent { event: glutin::WindowEvent::Focused(focused), .. } => {
+          self.focused = focused;
+        }
 
-        if let Event::WindowEvent { event: glutin::WindowEvent::Closed, .. } = event {
-          glutin::ControlFlow::Break
-        } else {
-          glutin::ControlFlow::Continue
+        // unlike get_inner_size, the new size is in physical pixels on every platform
+        Event::WindowEvent { event: glutin::WindowEvent::Resized(w, h), .. } => {
+          // required on some platforms, such as Wayland, and harmless on the others
+          self.window.resize(w, h);
+          self.resized = Some([w, h]);
         }
-      });
-    });
 
-    let device =
-      GlutinDevice {
-        events_rx,
-        window: gl_window,
-        event_thread
-      };
+        Event::WindowEvent { event: glutin::WindowEvent::HiDPIFactorChanged(factor), .. } => {
+          self.hidpi_factor_changed = Some(factor);
 
-    Ok(device)
-  }
+          // the logical size is kept, so the framebuffer size changes
+          let [w, h] = self.framebuffer_size();
+          self.window.resize(w, h);
+          self.resized = Some([w, h]);
+        }
 
-  fn size(&self) -> [u32; 2] {
-    let (w, h) = self.window.get_inner_size().unwrap_or((0, 0));
+        _ => ()
+      }
 
-    [w, h]
-  }
+      self.input_streams.dispatch(event);
+      self.input_state.update(event);
+      self.text_input.update(event);
+    }
 
-  fn events<'a>(&'a mut self) -> Box<Iterator<Item = Self::Event> + 'a> {
-    Box::new(self.events_rx.try_iter())
+    Box::new(events.into_iter())
   }
 
   fn draw<F>(&mut self, f: F) where F: FnOnce() {
+    let start = Instant::now();
+    let gpu_time = self.gpu_timer.as_mut().and_then(|gpu_timer| gpu_timer.begin());
+
     f();
-    self.window.swap_buffers();
+
+    if let Some(ref mut gpu_timer) = self.gpu_timer {
+      gpu_timer.end();
+    }
+
+    let cpu_time = start.elapsed();
+
+    if self.recorder.is_some() {
+      let size = self.framebuffer_size();
+      let result = self.recorder.as_mut().map_or(Ok(()), |recorder| recorder.record(size));
+
+      if let Err(e) = result {
+        if let Some(recorder) = self.recorder.take() {
+          recorder.abort();
+        }
+
+        self.record_error = Some(e);
+      }
+    }
+
+    let _ = self.window.swap_buffers();
+
+    let frame_period =
+      if self.focused {
+        self.frame_period
+      } else {
+        self.unfocused_frame_period.or(self.frame_period)
+      };
+
+    self.limiter.wait(frame_period);
+    self.frame_stats.end_frame(cpu_time, gpu_time);
+  }
+}
+
+impl Drop for GlutinDevice {
+  fn drop(&mut self) {
+    // pending frames must be written and GL objects released while the context is still alive
+    let _ = self.stop_recording();
+    self.set_gpu_timing(false);
   }
 }
+
+/// Convert a window size returned by `get_inner_size` into physical pixels.
+///
+/// `get_inner_size` returns points on macOS, and pixels everywhere else. `WindowEvent::Resized`
+/// always carries pixels and must not go through this.
+#[cfg(target_os = "macos")]
+fn to_physical_size((w, h): (u32, u32), factor: f32) -> [u32; 2] {
+  [(w as f32 * factor).round() as u32, (h as f32 * factor).round() as u32]
+}
+
+#[cfg(not(target_os = "macos"))]
+fn to_physical_size((w, h): (u32, u32), _: f32) -> [u32; 2] {
+  [w, h]
+}
@@ -0,0 +1,75 @@
+//! Frame rate limiter.
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Below that remaining time, spin instead of sleeping, as sleeping isn’t precise enough.
+const SPIN_THRESHOLD_MS: u64 = 2;
+
+/// Frame rate limiter.
+pub(crate) struct FrameLimiter {
+  /// When the next frame is allowed to be presented.
+  next_frame: Option<Instant>,
+}
+
+impl FrameLimiter {
+  pub(crate) fn new() -> Self {
+    FrameLimiter { next_frame: None }
+  }
+
+  /// Wait until `period` has elapsed since the previous frame, if any.
+  pub(crate) fn wait(&mut self, period: Option<Duration>) {
+    let period =
+      match period {
+        Some(period) => period,
+        None => {
+          self.next_frame = None;
+          return;
+        }
+      };
+
+    let deadline =
+      match self.next_frame {
+        Some(next_frame) => next_frame,
+        None => Instant::now()
+      };
+
+    wait_until(deadline);
+
+    // if we’re more than a frame late, don’t try to catch up with a burst of frames
+    let now = Instant::now();
+    let next_frame = deadline + period;
+    self.next_frame = Some(if next_frame < now { now + period } else { next_frame });
+  }
+}
+
+/// Sleep, then spin, until a given instant.
+fn wait_until(deadline: Instant) {
+  let spin_threshold = Duration::from_millis(SPIN_THRESHOLD_MS);
+
+  loop {
+    let now = Instant::now();
+
+    if now >= deadline {
+      break;
+    }
+
+    let remaining = deadline - now;
+
+    if remaining > spin_threshold {
+      thread::sleep(remaining - spin_threshold);
+    } else {
+      thread::yield_now();
+    }
+  }
+}
+
+/// Frame period for a given frame rate; `None` for non-positive rates.
+pub(crate) fn fps_to_period(fps: f64) -> Option<Duration> {
+  if fps > 0. {
+    let secs = 1. / fps;
+    Some(Duration::new(secs.trunc() as u64, (secs.fract() * 1e9) as u32))
+  } else {
+    None
+  }
+}
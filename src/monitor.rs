@@ -0,0 +1,76 @@
+//! Monitors.
+
+use glutin::{EventsLoop, MonitorId};
+
+/// A monitor, as enumerated by `GlutinDevice::monitors` or `GlutinDeviceBuilder::monitors`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Monitor {
+  index: usize,
+  name: Option<String>,
+  position: [i32; 2],
+  size: [u32; 2],
+  hidpi_factor: f32,
+}
+
+impl Monitor {
+  fn new(index: usize, id: &MonitorId) -> Self {
+    let (x, y) = id.get_position();
+    let (w, h) = id.get_dimensions();
+
+    Monitor {
+      index,
+      name: id.get_name(),
+      position: [x, y],
+      size: [w, h],
+      hidpi_factor: id.get_hidpi_factor(),
+    }
+  }
+
+  /// Index of the monitor in the list of available monitors.
+  ///
+  /// A monitor is only usable as long as the monitor at that index has the same name and position.
+  /// Enumerate the monitors again after they changed.
+  pub fn index(&self) -> usize {
+    self.index
+  }
+
+  /// Human-readable name of the monitor, if any.
+  pub fn name(&self) -> Option<&str> {
+    self.name.as_ref().map(String::as_str)
+  }
+
+  /// Position of the top-left corner of the monitor on the desktop, in pixels.
+  pub fn position(&self) -> [i32; 2] {
+    self.position
+  }
+
+  /// Size of the monitor, in pixels.
+  pub fn size(&self) -> [u32; 2] {
+    self.size
+  }
+
+  /// Ratio between physical pixels and logical units of the monitor.
+  pub fn hidpi_factor(&self) -> f32 {
+    self.hidpi_factor
+  }
+}
+
+/// Enumerate the available monitors.
+pub(crate) fn monitors(events_loop: &EventsLoop) -> Vec<Monitor> {
+  events_loop.get_available_monitors().enumerate().map(|(i, id)| Monitor::new(i, &id)).collect()
+}
+
+/// Get the glutin identifier of a monitor.
+///
+/// Returns `None` if the monitor at that index is not the same one anymore (e.g. after a monitor
+/// was plugged or unplugged), rather than silently picking another one.
+pub(crate) fn monitor_id(events_loop: &EventsLoop, monitor: &Monitor) -> Option<MonitorId> {
+  let id = events_loop.get_available_monitors().nth(monitor.index)?;
+  let (x, y) = id.get_position();
+
+  if id.get_name() == monitor.name && [x, y] == monitor.position {
+    Some(id)
+  } else {
+    None
+  }
+}
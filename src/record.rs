@@ -0,0 +1,220 @@
+//! Frame recording.
+//!
+//! Frames are read back asynchronously into a ring of pixel buffer objects (PBOs): the frame read
+//! during a `draw` is only mapped and written a few frames later, once the GPU is done with it, so
+//! that recording doesn’t stall the render loop.
+
+use gl;
+use gl::types::{GLsizeiptr, GLuint};
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::ptr;
+use std::slice;
+
+use capture::{self, Screenshot};
+
+/// Number of PBOs in the ring, i.e. how many frames late they’re written.
+const PBO_COUNT: usize = 3;
+
+/// Where recorded frames go.
+pub enum RecordSink {
+  /// Numbered PPM images (`frame-000000.ppm`, `frame-000001.ppm`, …) in a directory, which is
+  /// created if needed.
+  ImageSequence(PathBuf),
+  /// Raw RGB8 frames, rows top to bottom, one after the other.
+  ///
+  /// That’s what ffmpeg expects with `-f rawvideo -pix_fmt rgb24 -s <width>x<height> -i -`, for
+  /// instance if you pass the stdin of an ffmpeg child process. As the stream has no header, all
+  /// frames must have the same size: the recording stops with an error if the framebuffer is
+  /// resized.
+  Raw(Box<Write>),
+}
+
+/// Frame recorder.
+pub(crate) struct Recorder {
+  sink: RecordSink,
+  /// PBOs, allocated for `size`.
+  pbos: Vec<GLuint>,
+  /// Whether each PBO holds a frame that wasn’t written yet.
+  pending: Vec<bool>,
+  /// Size of the frames currently held by the PBOs.
+  size: [u32; 2],
+  /// Next PBO to read into; also the oldest pending one.
+  next: usize,
+  /// Number of frames written so far.
+  frame: u64,
+}
+
+impl Recorder {
+  pub(crate) fn new(sink: RecordSink) -> io::Result<Self> {
+    if let RecordSink::ImageSequence(ref dir) = sink {
+      fs::create_dir_all(dir)?;
+    }
+
+    let recorder =
+      Recorder {
+        sink,
+        pbos: Vec::new(),
+        pending: Vec::new(),
+        size: [0, 0],
+        next: 0,
+        frame: 0
+      };
+
+    Ok(recorder)
+  }
+
+  /// Number of frames written so far.
+  pub(crate) fn frame_count(&self) -> u64 {
+    self.frame
+  }
+
+  /// Start reading the current frame back, and write the oldest pending one.
+  ///
+  /// Must be called before swapping buffers.
+  pub(crate) fn record(&mut self, size: [u32; 2]) -> io::Result<()> {
+    // nothing to read back, e.g. while the window is minimized
+    if size[0] == 0 || size[1] == 0 {
+      return Ok(());
+    }
+
+    if size != self.size {
+      self.flush()?;
+
+      if let RecordSink::Raw(_) = self.sink {
+        if self.size != [0, 0] {
+          let [w, h] = self.size;
+          let msg = format!("framebuffer resized from {}x{} to {}x{} during a raw recording", w, h, size[0], size[1]);
+          return Err(io::Error::new(io::ErrorKind::InvalidInput, msg));
+        }
+      }
+
+      self.reallocate(size);
+    }
+
+    let index = self.next;
+
+    if self.pending[index] {
+      self.write_pbo(index)?;
+    }
+
+    unsafe { capture::read_pixels(self.pbos[index], size[0], size[1], ptr::null_mut()) };
+    self.pending[index] = true;
+    self.next = (index + 1) % PBO_COUNT;
+
+    Ok(())
+  }
+
+  /// Write all pending frames, release the PBOs and flush the sink.
+  pub(crate) fn finish(mut self) -> io::Result<()> {
+    let result = self.flush();
+    self.reallocate([0, 0]);
+    result?;
+
+    match self.sink {
+      RecordSink::Raw(ref mut w) => w.flush(),
+      RecordSink::ImageSequence(_) => Ok(())
+    }
+  }
+
+  /// Release the PBOs without writing the pending frames, after an error.
+  pub(crate) fn abort(mut self) {
+    self.reallocate([0, 0]);
+  }
+
+  /// Write all pending frames, oldest first.
+  fn flush(&mut self) -> io::Result<()> {
+    for i in 0 .. self.pending.len() {
+      let index = (self.next + i) % PBO_COUNT;
+
+      if self.pending[index] {
+        self.write_pbo(index)?;
+      }
+    }
+
+    Ok(())
+  }
+
+  /// Release the PBOs and allocate new ones for frames of the given size, if not empty.
+  fn reallocate(&mut self, size: [u32; 2]) {
+    unsafe {
+      if !self.pbos.is_empty() {
+        gl::DeleteBuffers(self.pbos.len() as _, self.pbos.as_ptr());
+        self.pbos.clear();
+        self.pending.clear();
+      }
+
+      if size[0] != 0 && size[1] != 0 {
+        let bytes = frame_bytes(size) as GLsizeiptr;
+        let mut previous_pack_buffer = 0;
+
+        self.pbos = vec![0; PBO_COUNT];
+        self.pending = vec![false; PBO_COUNT];
+
+        gl::GetIntegerv(gl::PIXEL_PACK_BUFFER_BINDING, &mut previous_pack_buffer);
+        gl::GenBuffers(PBO_COUNT as _, self.pbos.as_mut_ptr());
+
+        for &pbo in &self.pbos {
+          gl::BindBuffer(gl::PIXEL_PACK_BUFFER, pbo);
+          gl::BufferData(gl::PIXEL_PACK_BUFFER, bytes, ptr::null(), gl::STREAM_READ);
+        }
+
+        gl::BindBuffer(gl::PIXEL_PACK_BUFFER, previous_pack_buffer as GLuint);
+      }
+    }
+
+    self.size = size;
+    self.next = 0;
+  }
+
+  /// Map a pending PBO and write its frame to the sink.
+  fn write_pbo(&mut self, index: usize) -> io::Result<()> {
+    let [w, h] = self.size;
+    let mut pixels = vec![0; frame_bytes(self.size)];
+
+    unsafe {
+      let mut previous_pack_buffer = 0;
+
+      gl::GetIntegerv(gl::PIXEL_PACK_BUFFER_BINDING, &mut previous_pack_buffer);
+      gl::BindBuffer(gl::PIXEL_PACK_BUFFER, self.pbos[index]);
+
+      let mapped = gl::MapBuffer(gl::PIXEL_PACK_BUFFER, gl::READ_ONLY) as *const u8;
+
+      if !mapped.is_null() {
+        pixels.copy_from_slice(slice::from_raw_parts(mapped, pixels.len()));
+        gl::UnmapBuffer(gl::PIXEL_PACK_BUFFER);
+      }
+
+      gl::BindBuffer(gl::PIXEL_PACK_BUFFER, previous_pack_buffer as GLuint);
+
+      self.pending[index] = false;
+
+      if mapped.is_null() {
+        return Err(io::Error::new(io::ErrorKind::Other, "cannot map the pixel buffer of a recorded frame"));
+      }
+    }
+
+    capture::flip_rows(&mut pixels, w, h);
+
+    match self.sink {
+      RecordSink::ImageSequence(ref dir) => {
+        let path = dir.join(format!("frame-{:06}.ppm", self.frame));
+        Screenshot::new(w, h, pixels).save_ppm(path)?;
+      }
+
+      RecordSink::Raw(ref mut writer) => {
+        capture::write_rgb(writer, &pixels)?;
+      }
+    }
+
+    self.frame += 1;
+
+    Ok(())
+  }
+}
+
+/// Size in bytes of an RGBA8 frame.
+fn frame_bytes(size: [u32; 2]) -> usize {
+  size[0] as usize * size[1] as usize * 4
+}
@@ -0,0 +1,206 @@
+//! Fixed-timestep main loop.
+
+use luminance_windowing::Device;
+use std::time::{Duration, Instant};
+
+use {Event, GlutinDevice};
+
+/// What the main loop should do next.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Loop {
+  /// Keep going.
+  Continue,
+  /// Stop the main loop with an exit status.
+  Exit(i32),
+}
+
+/// Application driven by a `Runner`.
+pub trait App {
+  /// Handle an event.
+  fn event(&mut self, _device: &mut GlutinDevice, _event: Event) -> Loop {
+    Loop::Continue
+  }
+
+  /// Advance the simulation by `dt` seconds.
+  ///
+  /// `dt` is always the timestep of the runner.
+  fn update(&mut self, device: &mut GlutinDevice, dt: f64) -> Loop;
+
+  /// Render a frame.
+  ///
+  /// `alpha`, in `[0; 1[`, is how far the current time is between the last update and the next
+  /// one; use it to interpolate between the previous and current simulation states.
+  fn render(&mut self, alpha: f64);
+
+  /// The window was closed; return the exit status.
+  fn close(&mut self, _device: &mut GlutinDevice) -> i32 {
+    0
+  }
+}
+
+/// Fixed-timestep main loop built on a `GlutinDevice`.
+///
+/// Each iteration polls the events, runs as many fixed updates as the elapsed time calls for, and
+/// renders once with `Device::draw`. If updates can’t keep up, at most `max_steps` of them run per
+/// iteration and the remaining time is dropped, so that the loop doesn’t spiral.
+pub struct Runner {
+  device: GlutinDevice,
+  timestep: Timestep,
+}
+
+impl Runner {
+  /// Create a runner updating at 60Hz, with at most 5 updates per frame.
+  pub fn new(device: GlutinDevice) -> Self {
+    Runner {
+      device,
+      timestep: Timestep::new(Duration::new(0, 1_000_000_000 / 60), 5),
+    }
+  }
+
+  /// Set the duration of an update.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `timestep` is zero.
+  pub fn with_timestep(self, timestep: Duration) -> Self {
+    assert!(timestep > Duration::new(0, 0), "the timestep cannot be zero");
+    let timestep = Timestep::new(timestep, self.timestep.max_steps);
+    Runner { timestep, ..self }
+  }
+
+  /// Set the maximum number of updates per frame.
+  ///
+  /// # Panics
+  ///
+  /// Panics if `max_steps` is zero.
+  pub fn with_max_steps(self, max_steps: u32) -> Self {
+    assert!(max_steps > 0, "the maximum number of updates per frame cannot be zero");
+    let timestep = Timestep::new(self.timestep.timestep, max_steps);
+    Runner { timestep, ..self }
+  }
+
+  /// Device driven by this runner.
+  pub fn device(&mut self) -> &mut GlutinDevice {
+    &mut self.device
+  }
+
+  /// Run the main loop until the application exits or the window is closed, and return the exit
+  /// status.
+  pub fn run<A>(mut self, app: &mut A) -> i32 where A: App {
+    let dt = duration_secs(self.timestep.timestep);
+    let mut last_time = Instant::now();
+
+    loop {
+      let events: Vec<_> = self.device.events().collect();
+
+      for event in events {
+        if let Loop::Exit(status) = app.event(&mut self.device, event) {
+          return status;
+        }
+      }
+
+      if self.device.is_closed() {
+        return app.close(&mut self.device);
+      }
+
+      let now = Instant::now();
+      let steps = self.timestep.advance(now - last_time);
+      last_time = now;
+
+      for _ in 0 .. steps {
+        if let Loop::Exit(status) = app.update(&mut self.device, dt) {
+          return status;
+        }
+      }
+
+      let alpha = self.timestep.alpha();
+      self.device.draw(|| app.render(alpha));
+    }
+  }
+}
+
+/// Fixed-timestep accumulator, deciding how many updates each frame runs.
+struct Timestep {
+  timestep: Duration,
+  max_steps: u32,
+  /// Time not yet consumed by updates.
+  accumulator: Duration,
+}
+
+impl Timestep {
+  fn new(timestep: Duration, max_steps: u32) -> Self {
+    Timestep {
+      timestep,
+      max_steps,
+      accumulator: Duration::new(0, 0),
+    }
+  }
+
+  /// Account for elapsed time and return the number of updates to run.
+  ///
+  /// At most `max_steps` updates are returned; the time they can’t catch up with is dropped.
+  fn advance(&mut self, elapsed: Duration) -> u32 {
+    self.accumulator += elapsed;
+
+    let mut steps = 0;
+
+    while self.accumulator >= self.timestep {
+      self.accumulator -= self.timestep;
+
+      if steps < self.max_steps {
+        steps += 1;
+      }
+    }
+
+    steps
+  }
+
+  /// How far the current time is between the last update and the next one, in `[0; 1[`.
+  fn alpha(&self) -> f64 {
+    duration_secs(self.accumulator) / duration_secs(self.timestep)
+  }
+}
+
+/// Duration in seconds.
+pub(crate) fn duration_secs(d: Duration) -> f64 {
+  d.as_secs() as f64 + d.subsec_nanos() as f64 * 1e-9
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn ms(ms: u64) -> Duration {
+    Duration::from_millis(ms)
+  }
+
+  fn assert_close(a: f64, b: f64) {
+    assert!((a - b).abs() < 1e-9, "{} != {}", a, b);
+  }
+
+  #[test]
+  fn steps_and_alpha() {
+    let mut timestep = Timestep::new(ms(10), 5);
+
+    assert_eq!(timestep.advance(ms(25)), 2);
+    assert_close(timestep.alpha(), 0.5);
+
+    assert_eq!(timestep.advance(ms(4)), 0);
+    assert_close(timestep.alpha(), 0.9);
+
+    assert_eq!(timestep.advance(ms(1)), 1);
+    assert_close(timestep.alpha(), 0.);
+  }
+
+  #[test]
+  fn catch_up_is_capped() {
+    let mut timestep = Timestep::new(ms(10), 3);
+
+    // the late updates are dropped, but not the time between updates
+    assert_eq!(timestep.advance(ms(57)), 3);
+    assert_close(timestep.alpha(), 0.7);
+
+    assert_eq!(timestep.advance(ms(3)), 1);
+    assert_close(timestep.alpha(), 0.);
+  }
+}
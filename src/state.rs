@@ -0,0 +1,326 @@
+//! Polled input state.
+
+use glutin::{self, DeviceEvent, ModifiersState, MouseButton, WindowEvent};
+use std::collections::HashSet;
+
+use input::scroll_delta;
+use {Action, Event, Key};
+
+/// Snapshot of the input state.
+///
+/// It’s updated while events are polled with `Device::events`. Every poll starts a new frame: the
+/// “just pressed” and “just released” keys, the scroll delta and the mouse motion only cover the
+/// events polled in that frame.
+#[derive(Clone, Debug)]
+pub struct InputState {
+  keys: HashSet<Key>,
+  keys_pressed: HashSet<Key>,
+  keys_released: HashSet<Key>,
+  scancodes: HashSet<u32>,
+  mouse_buttons: HashSet<MouseButton>,
+  mouse_position: [f32; 2],
+  scroll_delta: [f32; 2],
+  mouse_motion: [f32; 2],
+  modifiers: ModifiersState,
+  /// Whether the window has the focus.
+  focused: bool,
+}
+
+impl InputState {
+  pub(crate) fn new() -> Self {
+    InputState {
+      keys: HashSet::new(),
+      keys_pressed: HashSet::new(),
+      keys_released: HashSet::new(),
+      scancodes: HashSet::new(),
+      mouse_buttons: HashSet::new(),
+      mouse_position: [0., 0.],
+      scroll_delta: [0., 0.],
+      mouse_motion: [0., 0.],
+      modifiers: ModifiersState::default(),
+      focused: true,
+    }
+  }
+
+  /// Whether a key is currently held down.
+  pub fn is_key_pressed(&self, key: Key) -> bool {
+    self.keys.contains(&key)
+  }
+
+  /// Whether a key was pressed during the last frame.
+  pub fn was_key_just_pressed(&self, key: Key) -> bool {
+    self.keys_pressed.contains(&key)
+  }
+
+  /// Whether a key was released during the last frame.
+  pub fn was_key_just_released(&self, key: Key) -> bool {
+    self.keys_released.contains(&key)
+  }
+
+  /// Keys currently held down.
+  pub fn keys(&self) -> &HashSet<Key> {
+    &self.keys
+  }
+
+  /// Whether the physical key with a given scancode is currently held down.
+  ///
+  /// Unlike `is_key_pressed`, this doesn’t depend on the keyboard layout.
+  pub fn is_scancode_pressed(&self, scancode: u32) -> bool {
+    self.scancodes.contains(&scancode)
+  }
+
+  /// Whether a mouse button is currently held down.
+  pub fn is_mouse_button_pressed(&self, button: MouseButton) -> bool {
+    self.mouse_buttons.contains(&button)
+  }
+
+  /// Mouse buttons currently held down.
+  pub fn mouse_buttons(&self) -> &HashSet<MouseButton> {
+    &self.mouse_buttons
+  }
+
+  /// Last known cursor position, in pixels, relative to the top-left corner of the window.
+  pub fn mouse_position(&self) -> [f32; 2] {
+    self.mouse_position
+  }
+
+  /// Scroll accumulated during the last frame, in lines or in pixels depending on the input device.
+  pub fn scroll_delta(&self) -> [f32; 2] {
+    self.scroll_delta
+  }
+
+  /// Relative mouse motion accumulated during the last frame.
+  ///
+  /// Unlike the cursor position, it comes straight from the mouse: it’s not accelerated and keeps
+  /// going when the cursor hits the edges of the screen or is grabbed, which makes it the right
+  /// input for a first-person camera. Its unit depends on the platform and mouse.
+  ///
+  /// Motion is ignored while the window doesn’t have the focus, as some platforms (e.g. X11)
+  /// report it regardless.
+  pub fn mouse_motion(&self) -> [f32; 2] {
+    self.mouse_motion
+  }
+
+  /// Last known state of the keyboard modifiers.
+  pub fn modifiers(&self) -> ModifiersState {
+    self.modifiers
+  }
+
+  /// Start a new frame.
+  pub(crate) fn new_frame(&mut self) {
+    self.keys_pressed.clear();
+    self.keys_released.clear();
+    self.scroll_delta = [0., 0.];
+    self.mouse_motion = [0., 0.];
+  }
+
+  /// Update the state with an event.
+  pub(crate) fn update(&mut self, event: &Event) {
+    match *event {
+      Event::WindowEvent { ref event, .. } => {
+        match *event {
+          WindowEvent::KeyboardInput { input: glutin::KeyboardInput { scancode, virtual_keycode, state, modifiers }, .. } => {
+            self.on_key(scancode, virtual_keycode, state, modifiers);
+          }
+
+          WindowEvent::MouseInput { state, button, modifiers, .. } => {
+            self.on_mouse_button(button, state, modifiers);
+          }
+
+          WindowEvent::CursorMoved { position: (x, y), modifiers, .. } => {
+            self.modifiers = modifiers;
+            self.mouse_position = [x as f32, y as f32];
+          }
+
+          WindowEvent::MouseWheel { delta, modifiers, .. } => {
+            self.on_scroll(scroll_delta(delta), modifiers);
+          }
+
+          WindowEvent::Focused(focused) => {
+            self.on_focus(focused);
+          }
+
+          _ => ()
+        }
+      }
+
+      Event::DeviceEvent { event: DeviceEvent::MouseMotion { delta: (dx, dy) }, .. } => {
+        self.on_mouse_motion(dx as f32, dy as f32);
+      }
+
+      _ => ()
+    }
+  }
+
+  fn on_key(&mut self, scancode: u32, key: Option<Key>, action: Action, modifiers: ModifiersState) {
+    self.modifiers = modifiers;
+
+    match action {
+      Action::Pressed => { self.scancodes.insert(scancode); }
+      Action::Released => { self.scancodes.remove(&scancode); }
+    }
+
+    if let Some(key) = key {
+      match action {
+        Action::Pressed => {
+          // key repeats don’t count as new presses
+          if self.keys.insert(key) {
+            self.keys_pressed.insert(key);
+          }
+        }
+
+        Action::Released => {
+          self.keys.remove(&key);
+          self.keys_released.insert(key);
+        }
+      }
+    }
+  }
+
+  fn on_mouse_button(&mut self, button: MouseButton, action: Action, modifiers: ModifiersState) {
+    self.modifiers = modifiers;
+
+    match action {
+      Action::Pressed => { self.mouse_buttons.insert(button); }
+      Action::Released => { self.mouse_buttons.remove(&button); }
+    }
+  }
+
+  fn on_scroll(&mut self, [x, y]: [f32; 2], modifiers: ModifiersState) {
+    self.modifiers = modifiers;
+    self.scroll_delta[0] += x;
+    self.scroll_delta[1] += y;
+  }
+
+  fn on_mouse_motion(&mut self, dx: f32, dy: f32) {
+    if self.focused {
+      self.mouse_motion[0] += dx;
+      self.mouse_motion[1] += dy;
+    }
+  }
+
+  fn on_focus(&mut self, focused: bool) {
+    self.focused = focused;
+
+    // we won’t get the release events of keys and buttons held while the window is unfocused
+    if !focused {
+      for key in self.keys.drain() {
+        self.keys_released.insert(key);
+      }
+
+      self.scancodes.clear();
+      self.mouse_buttons.clear();
+      self.modifiers = ModifiersState::default();
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn press(state: &mut InputState, key: Key) {
+    state.on_key(0, Some(key), Action::Pressed, ModifiersState::default());
+  }
+
+  fn release(state: &mut InputState, key: Key) {
+    state.on_key(0, Some(key), Action::Released, ModifiersState::default());
+  }
+
+  #[test]
+  fn just_pressed_and_released() {
+    let mut state = InputState::new();
+
+    state.new_frame();
+    press(&mut state, Key::A);
+    assert!(state.is_key_pressed(Key::A));
+    assert!(state.was_key_just_pressed(Key::A));
+
+    state.new_frame();
+    assert!(state.is_key_pressed(Key::A));
+    assert!(!state.was_key_just_pressed(Key::A));
+
+    release(&mut state, Key::A);
+    assert!(!state.is_key_pressed(Key::A));
+    assert!(state.was_key_just_released(Key::A));
+
+    state.new_frame();
+    assert!(!state.was_key_just_released(Key::A));
+  }
+
+  #[test]
+  fn repeat_is_not_a_new_press() {
+    let mut state = InputState::new();
+
+    press(&mut state, Key::A);
+    state.new_frame();
+    press(&mut state, Key::A);
+
+    assert!(state.is_key_pressed(Key::A));
+    assert!(!state.was_key_just_pressed(Key::A));
+  }
+
+  #[test]
+  fn focus_lost_releases_everything() {
+    let mut state = InputState::new();
+
+    press(&mut state, Key::A);
+    state.on_mouse_button(MouseButton::Left, Action::Pressed, ModifiersState::default());
+    state.new_frame();
+    state.on_focus(false);
+
+    assert!(state.keys().is_empty());
+    assert!(state.was_key_just_released(Key::A));
+    assert!(state.mouse_buttons().is_empty());
+  }
+
+  #[test]
+  fn scroll_accumulates() {
+    let mut state = InputState::new();
+
+    state.on_scroll([1., -2.], ModifiersState::default());
+    state.on_scroll([0.5, 1.], ModifiersState::default());
+    assert_eq!(state.scroll_delta(), [1.5, -1.]);
+
+    state.new_frame();
+    assert_eq!(state.scroll_delta(), [0., 0.]);
+  }
+
+  #[test]
+  fn mouse_motion_accumulates_while_focused() {
+    let mut state = InputState::new();
+
+    state.on_mouse_motion(3., 4.);
+    state.on_mouse_motion(-1., 2.);
+    assert_eq!(state.mouse_motion(), [2., 6.]);
+
+    state.new_frame();
+    assert_eq!(state.mouse_motion(), [0., 0.]);
+
+    // some platforms report raw motion while unfocused
+    state.on_focus(false);
+    state.on_mouse_motion(5., 5.);
+    assert_eq!(state.mouse_motion(), [0., 0.]);
+
+    state.on_focus(true);
+    state.on_mouse_motion(5., 5.);
+    assert_eq!(state.mouse_motion(), [5., 5.]);
+  }
+
+  #[test]
+  fn scancodes() {
+    let mut state = InputState::new();
+
+    // keys without virtual key code are only known by their scancode
+    state.on_key(0x1d0, None, Action::Pressed, ModifiersState::default());
+    assert!(state.is_scancode_pressed(0x1d0));
+    assert!(state.keys().is_empty());
+
+    state.on_key(0x1d0, None, Action::Released, ModifiersState::default());
+    assert!(!state.is_scancode_pressed(0x1d0));
+
+    state.on_key(30, Some(Key::A), Action::Pressed, ModifiersState::default());
+    state.on_focus(false);
+    assert!(!state.is_scancode_pressed(30));
+  }
+}
@@ -0,0 +1,236 @@
+//! Frame timing statistics.
+
+use gl;
+use gl::types::{GLint, GLuint, GLuint64};
+use std::collections::VecDeque;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::time::{Duration, Instant};
+
+use builder::get_gl_version;
+use runner::duration_secs;
+
+/// Number of frames the rolling statistics are computed over.
+const WINDOW: usize = 120;
+
+/// Number of GPU timer queries in flight.
+const QUERY_COUNT: usize = 3;
+
+/// Frame timing statistics.
+///
+/// Updated by every `Device::draw`.
+#[derive(Clone, Debug)]
+pub struct FrameStats {
+  frame_count: u64,
+  cpu_time: Duration,
+  frame_time: Duration,
+  gpu_time: Option<Duration>,
+  last_swap: Option<Instant>,
+  frame_times: VecDeque<Duration>,
+}
+
+impl FrameStats {
+  pub(crate) fn new() -> Self {
+    FrameStats {
+      frame_count: 0,
+      cpu_time: Duration::new(0, 0),
+      frame_time: Duration::new(0, 0),
+      gpu_time: None,
+      last_swap: None,
+      frame_times: VecDeque::with_capacity(WINDOW),
+    }
+  }
+
+  /// Number of frames drawn so far.
+  pub fn frame_count(&self) -> u64 {
+    self.frame_count
+  }
+
+  /// Time spent in the closure passed to the last `Device::draw`.
+  pub fn cpu_time(&self) -> Duration {
+    self.cpu_time
+  }
+
+  /// Time between the last two buffer swaps.
+  pub fn frame_time(&self) -> Duration {
+    self.frame_time
+  }
+
+  /// Time the GPU spent on the commands issued in a recent `Device::draw` closure, if GPU timing is
+  /// enabled.
+  ///
+  /// GPU timings are read back without stalling, so they lag a couple of frames behind.
+  pub fn gpu_time(&self) -> Option<Duration> {
+    self.gpu_time
+  }
+
+  /// Average number of frames per second over the last frames.
+  pub fn fps(&self) -> f64 {
+    let total = self.frame_times.iter().fold(Duration::new(0, 0), |total, &t| total + t);
+    let total = duration_secs(total);
+
+    if total > 0. {
+      self.frame_times.len() as f64 / total
+    } else {
+      0.
+    }
+  }
+
+  /// Shortest frame time over the last frames.
+  pub fn min_frame_time(&self) -> Duration {
+    self.frame_times.iter().cloned().min().unwrap_or_else(|| Duration::new(0, 0))
+  }
+
+  /// Longest frame time over the last frames.
+  pub fn max_frame_time(&self) -> Duration {
+    self.frame_times.iter().cloned().max().unwrap_or_else(|| Duration::new(0, 0))
+  }
+
+  /// Record a frame whose buffers were just swapped.
+  pub(crate) fn end_frame(&mut self, cpu_time: Duration, gpu_time: Option<Duration>) {
+    let now = Instant::now();
+
+    if let Some(last_swap) = self.last_swap {
+      self.frame_time = now - last_swap;
+
+      if self.frame_times.len() == WINDOW {
+        self.frame_times.pop_front();
+      }
+
+      self.frame_times.push_back(self.frame_time);
+    }
+
+    self.frame_count += 1;
+    self.cpu_time = cpu_time;
+    self.last_swap = Some(now);
+
+    if gpu_time.is_some() {
+      self.gpu_time = gpu_time;
+    }
+  }
+
+  pub(crate) fn clear_gpu_time(&mut self) {
+    self.gpu_time = None;
+  }
+}
+
+/// GPU timer based on `GL_TIME_ELAPSED` queries.
+///
+/// Several queries are kept in flight so that reading a result never waits for the GPU.
+pub(crate) struct GpuTimer {
+  queries: [GLuint; QUERY_COUNT],
+  pending: [bool; QUERY_COUNT],
+  next: usize,
+  /// Whether the current frame is being timed.
+  timing: bool,
+}
+
+impl GpuTimer {
+  /// Create a timer, if the current context supports timer queries.
+  pub(crate) fn new() -> Option<Self> {
+    if !timer_queries_supported() {
+      return None;
+    }
+
+    let mut queries = [0; QUERY_COUNT];
+    unsafe { gl::GenQueries(QUERY_COUNT as _, queries.as_mut_ptr()) };
+
+    let timer =
+      GpuTimer {
+        queries,
+        pending: [false; QUERY_COUNT],
+        next: 0,
+        timing: false,
+      };
+
+    Some(timer)
+  }
+
+  /// Start timing a frame, returning the result of an older frame if it’s available.
+  ///
+  /// If the oldest query isn’t available yet, this frame is not timed.
+  pub(crate) fn begin(&mut self) -> Option<Duration> {
+    let query = self.queries[self.next];
+    let mut result = None;
+
+    if self.pending[self.next] {
+      let mut available: GLint = 0;
+      unsafe { gl::GetQueryObjectiv(query, gl::QUERY_RESULT_AVAILABLE, &mut available) };
+
+      if available == 0 {
+        return None;
+      }
+
+      let mut nanos: GLuint64 = 0;
+      unsafe { gl::GetQueryObjectui64v(query, gl::QUERY_RESULT, &mut nanos) };
+
+      self.pending[self.next] = false;
+      result = Some(Duration::new(nanos / 1_000_000_000, (nanos % 1_000_000_000) as u32));
+    }
+
+    unsafe { gl::BeginQuery(gl::TIME_ELAPSED, query) };
+    self.timing = true;
+
+    result
+  }
+
+  /// Stop timing the current frame.
+  pub(crate) fn end(&mut self) {
+    if self.timing {
+      unsafe { gl::EndQuery(gl::TIME_ELAPSED) };
+      self.pending[self.next] = true;
+      self.next = (self.next + 1) % QUERY_COUNT;
+      self.timing = false;
+    }
+  }
+
+  /// Release the queries.
+  pub(crate) fn delete(self) {
+    unsafe { gl::DeleteQueries(QUERY_COUNT as _, self.queries.as_ptr()) };
+  }
+}
+
+/// Whether the current context supports `GL_TIME_ELAPSED` queries.
+///
+/// The `gl` crate panics when calling a function that wasn’t loaded, so this must be checked before
+/// creating a `GpuTimer`.
+fn timer_queries_supported() -> bool {
+  let loaded =
+    gl::GenQueries::is_loaded() && gl::DeleteQueries::is_loaded() && gl::BeginQuery::is_loaded() &&
+    gl::EndQuery::is_loaded() && gl::GetQueryObjectiv::is_loaded() && gl::GetQueryObjectui64v::is_loaded();
+
+  if !loaded {
+    return false;
+  }
+
+  let version = unsafe { gl::GetString(gl::VERSION) };
+
+  // OpenGL ES only has timer queries through EXT_disjoint_timer_query, with other entry points
+  if version.is_null() || unsafe { CStr::from_ptr(version as *const c_char) }.to_bytes().starts_with(b"OpenGL ES") {
+    return false;
+  }
+
+  match get_gl_version() {
+    Some(version) if version >= (3, 3) => true,
+    _ => has_extension("GL_ARB_timer_query")
+  }
+}
+
+/// Whether the current context exposes an extension.
+fn has_extension(name: &str) -> bool {
+  unsafe {
+    // glGetString(GL_EXTENSIONS) is not available in core profiles
+    if gl::GetStringi::is_loaded() {
+      let mut count: GLint = 0;
+      gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+
+      (0 .. count.max(0) as GLuint).any(|i| {
+        let ext = gl::GetStringi(gl::EXTENSIONS, i);
+        !ext.is_null() && CStr::from_ptr(ext as *const c_char).to_bytes() == name.as_bytes()
+      })
+    } else {
+      let exts = gl::GetString(gl::EXTENSIONS);
+      !exts.is_null() && CStr::from_ptr(exts as *const c_char).to_bytes().split(|&c| c == b' ').any(|ext| ext == name.as_bytes())
+    }
+  }
+}
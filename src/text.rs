@@ -0,0 +1,157 @@
+//! Text input.
+
+use glutin::WindowEvent;
+use std::char;
+
+use Event;
+
+/// Text input.
+///
+/// Collects the characters the platform composed (after dead keys and input methods) from
+/// `WindowEvent::ReceivedCharacter`, dropping the ones that are not text.
+///
+/// On Windows, glutin sends each UTF-16 code unit as a `char`, so characters outside the Basic
+/// Multilingual Plane (emoji, some CJK) arrive as two surrogate halves, which are paired back here.
+pub(crate) struct TextInput {
+  enabled: bool,
+  text: String,
+  /// High surrogate waiting for its low surrogate.
+  high_surrogate: Option<u32>,
+}
+
+impl TextInput {
+  pub(crate) fn new() -> Self {
+    TextInput {
+      enabled: false,
+      text: String::new(),
+      high_surrogate: None,
+    }
+  }
+
+  pub(crate) fn set_enabled(&mut self, enabled: bool) {
+    self.enabled = enabled;
+
+    if !enabled {
+      self.text.clear();
+      self.high_surrogate = None;
+    }
+  }
+
+  pub(crate) fn is_enabled(&self) -> bool {
+    self.enabled
+  }
+
+  pub(crate) fn text(&self) -> &str {
+    &self.text
+  }
+
+  /// Start a new frame.
+  pub(crate) fn new_frame(&mut self) {
+    self.text.clear();
+  }
+
+  /// Update the text with an event.
+  pub(crate) fn update(&mut self, event: &Event) {
+    if let Event::WindowEvent { event: WindowEvent::ReceivedCharacter(c), .. } = *event {
+      self.push_code_unit(c as u32);
+    }
+  }
+
+  /// Add a received character, which may be a UTF-16 surrogate half.
+  fn push_code_unit(&mut self, code: u32) {
+    if !self.enabled {
+      return;
+    }
+
+    // unpaired surrogate halves are dropped
+    let c =
+      match code {
+        0xd800 ..= 0xdbff => {
+          self.high_surrogate = Some(code);
+          return;
+        }
+
+        0xdc00 ..= 0xdfff => {
+          match self.high_surrogate.take() {
+            Some(high) => char::from_u32(0x10000 + ((high - 0xd800) << 10) + (code - 0xdc00)),
+            None => None
+          }
+        }
+
+        _ => {
+          self.high_surrogate = None;
+          char::from_u32(code)
+        }
+      };
+
+    if let Some(c) = c.filter(|&c| is_text(c)) {
+      self.text.push(c);
+    }
+  }
+}
+
+/// Whether a received character is text, as opposed to control characters (backspace, return,
+/// escape, delete…) and the private-use characters macOS sends for function and arrow keys.
+fn is_text(c: char) -> bool {
+  !c.is_control() && !('\u{f700}' <= c && c <= '\u{f8ff}')
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn push_str(text_input: &mut TextInput, s: &str) {
+    for c in s.chars() {
+      text_input.push_code_unit(c as u32);
+    }
+  }
+
+  #[test]
+  fn disabled() {
+    let mut text_input = TextInput::new();
+
+    push_str(&mut text_input, "abc");
+    assert_eq!(text_input.text(), "");
+
+    text_input.set_enabled(true);
+    push_str(&mut text_input, "abc");
+    assert_eq!(text_input.text(), "abc");
+
+    text_input.set_enabled(false);
+    assert_eq!(text_input.text(), "");
+  }
+
+  #[test]
+  fn new_frame_clears() {
+    let mut text_input = TextInput::new();
+    text_input.set_enabled(true);
+
+    push_str(&mut text_input, "é");
+    text_input.new_frame();
+    push_str(&mut text_input, "ü");
+    assert_eq!(text_input.text(), "ü");
+  }
+
+  #[test]
+  fn control_characters_are_dropped() {
+    let mut text_input = TextInput::new();
+    text_input.set_enabled(true);
+
+    // backspace, tab, return, escape, delete, then macOS’ up arrow and F1
+    push_str(&mut text_input, "a\u{8}\t\r\u{1b}\u{7f}\u{f700}\u{f704}b");
+    assert_eq!(text_input.text(), "ab");
+  }
+
+  #[test]
+  fn surrogate_pairs() {
+    let mut text_input = TextInput::new();
+    text_input.set_enabled(true);
+
+    // U+1F600 as a pair, a lone low half, then two high halves not followed by a low one
+    for &code in &[0xd83d, 0xde00, 0xde00, 0x61, 0xd83d, 0xd83d, 0x62] {
+      text_input.push_code_unit(code);
+    }
+
+    assert_eq!(text_input.text(), "\u{1f600}ab");
+  }
+}
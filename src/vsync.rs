@@ -0,0 +1,163 @@
+//! Vertical synchronization.
+//!
+//! glutin only lets us request vsync when creating the context, only as a boolean, and silently
+//! ignores it when the platform can’t do it. Where we can (WGL and GLX), the swap interval is then
+//! set again by loading the platform’s swap interval extension ourselves, which also lets us change
+//! it afterwards, ask for adaptive vsync and know whether it worked. Elsewhere (macOS, EGL), we can
+//! only rely on what glutin did at creation.
+
+use glutin;
+use std::os::raw::c_int;
+
+/// Vertical synchronization mode.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Vsync {
+  /// Swap buffers as soon as possible, without waiting for the vertical blank.
+  Off,
+  /// Wait for the vertical blank before swapping buffers.
+  On,
+  /// Wait for the vertical blank, unless the frame is late, in which case swap immediately.
+  ///
+  /// Requires the `EXT_swap_control_tear` extension.
+  Adaptive,
+}
+
+impl Vsync {
+  /// Swap interval corresponding to this mode.
+  fn interval(self) -> c_int {
+    match self {
+      Vsync::Off => 0,
+      Vsync::On => 1,
+      Vsync::Adaptive => -1,
+    }
+  }
+}
+
+/// Change the swap interval of the window’s context, which must be current.
+///
+/// Returns whether the platform accepted it, or `None` if the swap interval can’t be set by hand on
+/// this platform.
+pub(crate) fn set_swap_interval(window: &glutin::GlWindow, vsync: Vsync) -> Option<bool> {
+  platform_set_swap_interval(window, vsync.interval())
+}
+
+#[cfg(windows)]
+fn platform_set_swap_interval(window: &glutin::GlWindow, interval: c_int) -> Option<bool> {
+  use glutin::GlContext;
+  use std::mem;
+
+  // unlike glXGetProcAddress, wglGetProcAddress returns null for unsupported extensions
+  let f = window.get_proc_address("wglSwapIntervalEXT");
+
+  if f.is_null() {
+    return Some(false);
+  }
+
+  // fails with a negative interval if WGL_EXT_swap_control_tear is not supported
+  let f: extern "system" fn(c_int) -> c_int = unsafe { mem::transmute(f) };
+  Some(f(interval) != 0)
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn platform_set_swap_interval(window: &glutin::GlWindow, interval: c_int) -> Option<bool> {
+  use glutin::GlContext;
+  use glutin::os::unix::WindowExt;
+  use std::ffi::CStr;
+  use std::mem;
+  use std::os::raw::{c_char, c_void};
+
+  // not on X11 (e.g. Wayland): the context is an EGL one, whose swap interval only glutin sets
+  let (display, screen) =
+    match (window.get_xlib_display(), window.get_xlib_screen_id()) {
+      (Some(display), Some(screen)) => (display, screen),
+      _ => return None
+    };
+
+  // glXGetProcAddress returns a non-null pointer for about any name, so the extension string is
+  // the only reliable way to know which extensions are there; if the context is an EGL one after
+  // all, the returned dispatch stub yields null
+  let query_extensions = window.get_proc_address("glXQueryExtensionsString");
+
+  if query_extensions.is_null() {
+    return None;
+  }
+
+  let query_extensions: extern "C" fn(*mut c_void, c_int) -> *const c_char = unsafe { mem::transmute(query_extensions) };
+  let extensions = query_extensions(display, screen);
+
+  if extensions.is_null() {
+    return None;
+  }
+
+  Some(glx_set_swap_interval(window, display, &unsafe { CStr::from_ptr(extensions) }.to_string_lossy(), interval))
+}
+
+/// Change the swap interval through the first GLX swap control extension that can do it.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn glx_set_swap_interval(
+  window: &glutin::GlWindow,
+  display: *mut ::std::os::raw::c_void,
+  extensions: &str,
+  interval: c_int
+) -> bool {
+  use glutin::GlContext;
+  use std::mem;
+  use std::os::raw::{c_uint, c_ulong, c_void};
+
+  let has_extension = |name: &str| extensions.split_whitespace().any(|ext| ext == name);
+
+  if has_extension("GLX_EXT_swap_control") && (interval >= 0 || has_extension("GLX_EXT_swap_control_tear")) {
+    let get_current_drawable = window.get_proc_address("glXGetCurrentDrawable");
+    let swap_interval = window.get_proc_address("glXSwapIntervalEXT");
+
+    if get_current_drawable.is_null() || swap_interval.is_null() {
+      return false;
+    }
+
+    let get_current_drawable: extern "C" fn() -> c_ulong = unsafe { mem::transmute(get_current_drawable) };
+    let swap_interval: extern "C" fn(*mut c_void, c_ulong, c_int) = unsafe { mem::transmute(swap_interval) };
+    let drawable = get_current_drawable();
+
+    if drawable == 0 {
+      return false;
+    }
+
+    swap_interval(display, drawable, interval);
+    return true;
+  }
+
+  // neither GLX_MESA_swap_control nor GLX_SGI_swap_control support negative intervals
+  if interval < 0 {
+    return false;
+  }
+
+  if has_extension("GLX_MESA_swap_control") {
+    let f = window.get_proc_address("glXSwapIntervalMESA");
+
+    if f.is_null() {
+      return false;
+    }
+
+    let f: extern "C" fn(c_uint) -> c_int = unsafe { mem::transmute(f) };
+    return f(interval as c_uint) == 0;
+  }
+
+  // GLX_SGI_swap_control cannot disable vsync
+  if interval > 0 && has_extension("GLX_SGI_swap_control") {
+    let f = window.get_proc_address("glXSwapIntervalSGI");
+
+    if f.is_null() {
+      return false;
+    }
+
+    let f: extern "C" fn(c_int) -> c_int = unsafe { mem::transmute(f) };
+    return f(interval) == 0;
+  }
+
+  false
+}
+
+#[cfg(not(any(windows, all(unix, not(target_os = "macos")))))]
+fn platform_set_swap_interval(_: &glutin::GlWindow, _: c_int) -> Option<bool> {
+  None
+}